@@ -0,0 +1,984 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::settings::Credentials;
+use crate::utils::{log_if_error, today, DEFAULT_TIMEZONE};
+use chrono_tz::Tz;
+use reqwest::multipart;
+
+// Largest page size Crowdlog accepts for lists.
+const PER_PAGE: u32 = 100;
+
+// Status of a ReqError when the server could not be reached at all, e.g. the VPN is down.
+const UNREACHABLE: u16 = 503;
+
+/// Error of a request to Crowdlog, or of the arguments given for it.
+#[derive(Debug, serde::Serialize)]
+pub struct ReqError {
+    /// HTTP status, 503 when Crowdlog could not be reached.
+    pub status: u16,
+    pub message: String,
+    /// Underlying error, if any.
+    pub source: Option<String>,
+}
+impl ReqError {
+    pub fn from(err: &reqwest::Error) -> Self {
+        let status = match err.status() {
+            Some(v) => v.into(),
+            None if err.is_connect() || err.is_timeout() => UNREACHABLE,
+            None => 500,
+        };
+
+        let source = err.source().map(|v| v.to_string());
+
+        Self {
+            status,
+            message: err.to_string(),
+            source,
+        }
+    }
+
+    /// True when Crowdlog could not be reached at all, e.g. offline or the VPN is down.
+    pub fn is_unreachable(&self) -> bool {
+        self.status == UNREACHABLE
+    }
+}
+
+/// The logged-in user.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct User {
+    pub id: u32,
+    pub email: String,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct Project {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct Process {
+    pub id: u32,
+    pub name: String,
+}
+
+/// Processes of a project form a tree, deeper levels are listed under a parent process.
+pub const MAX_PROCESS_LEVEL: u8 = 3;
+
+/// What the time is recorded for: a project, its processes and a memo.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct WorkContent {
+    project: Option<Project>,
+    // Level 1 process.
+    process: Option<Process>,
+    // Deeper levels, only set when the tenant uses them.
+    #[serde(default)]
+    process_2: Option<Process>,
+    #[serde(default)]
+    process_3: Option<Process>,
+    // Description of the work, required by some billing processes.
+    #[serde(default)]
+    memo: String,
+}
+impl WorkContent {
+    pub fn new(project: Project, process: Process) -> Self {
+        Self {
+            project: Some(project),
+            process: Some(process),
+            process_2: None,
+            process_3: None,
+            memo: String::new(),
+        }
+    }
+
+    /// Processes of level 2 and 3, in order.
+    pub fn with_sub_processes(self, sub_processes: Vec<Process>) -> Self {
+        let mut levels = sub_processes.into_iter();
+        Self {
+            process_2: levels.next(),
+            process_3: levels.next(),
+            ..self
+        }
+    }
+
+    pub fn with_memo(self, memo: &str) -> Self {
+        Self {
+            memo: memo.to_string(),
+            ..self
+        }
+    }
+
+    pub fn memo(&self) -> &str {
+        &self.memo
+    }
+
+    /// Names of the project and the process, if both are set. Crowdlog sends an id of 0 for unset ones.
+    pub fn names(&self) -> Option<(&str, &str)> {
+        match (&self.project, &self.process) {
+            (Some(pj), Some(pc)) if pj.id > 0 && pc.id > 0 => Some((&pj.name, &pc.name)),
+            _ => None,
+        }
+    }
+
+    /// Ids of the project and the level 1 process, if both are set.
+    pub fn ids(&self) -> Option<(u32, u32)> {
+        match (&self.project, &self.process) {
+            (Some(pj), Some(pc)) if pj.id > 0 && pc.id > 0 => Some((pj.id, pc.id)),
+            _ => None,
+        }
+    }
+
+    /// Processes from level 1 down to the deepest set one.
+    pub fn process_path(&self) -> Vec<&Process> {
+        [&self.process, &self.process_2, &self.process_3]
+            .into_iter()
+            .map_while(|v| v.as_ref().filter(|pc| pc.id > 0))
+            .collect()
+    }
+
+    pub fn process_ids(&self) -> Vec<u32> {
+        self.process_path().iter().map(|pc| pc.id).collect()
+    }
+
+    /// The project and every level of process, as shown outside of the window. `-` when unset.
+    pub fn label(&self) -> String {
+        match self.names() {
+            Some((project, _)) => {
+                let mut names = vec![project];
+                names.extend(self.process_path().iter().map(|pc| pc.name.as_str()));
+                names.join(" ▶ ")
+            }
+            None => String::from("-"),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub enum StopWatchStatus {
+    #[serde(rename = "timing")]
+    Started,
+    /// Stopped, the time is recorded once it's applied, or dropped when it's reset.
+    #[serde(rename = "stopped")]
+    NeedToApply,
+    #[serde(rename = "reset")]
+    Clean,
+}
+/// The stopwatch of the user, Crowdlog has a single one per user.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct StopWatch {
+    pub id: u32,
+    pub start_at: String,
+    /// Only set while the stopwatch is stopped and not applied yet.
+    #[serde(default)]
+    pub stop_at: Option<String>,
+    pub status: StopWatchStatus,
+    pub work_content: WorkContent,
+}
+
+/// An entry of the recorded time.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct Timesheet {
+    pub id: u32,
+    /// Format: `%Y-%m-%d`.
+    pub date: String,
+    /// Format: `%Y-%m-%d %H:%M:%S`. Entries can be recorded with a duration only.
+    #[serde(default)]
+    pub start_at: Option<String>,
+    #[serde(default)]
+    pub end_at: Option<String>,
+    /// In minutes.
+    pub duration: u32,
+    #[serde(default)]
+    pub memo: String,
+    pub work_content: WorkContent,
+}
+
+impl Timesheet {
+    /// Its fields, to edit it. None if it has no work content.
+    pub fn to_input(&self) -> Option<TimesheetInput> {
+        let (project_id, process_id) = self.work_content.ids()?;
+        Some(TimesheetInput {
+            date: self.date.clone(),
+            project_id,
+            process_id,
+            sub_process_ids: self
+                .work_content
+                .process_ids()
+                .into_iter()
+                .skip(1)
+                .collect(),
+            start_at: self.start_at.clone(),
+            end_at: self.end_at.clone(),
+            duration: self.duration,
+            memo: self.memo.clone(),
+        })
+    }
+}
+
+/// Fields of a timesheet entry to create or edit.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct TimesheetInput {
+    pub date: String,
+    pub project_id: u32,
+    pub process_id: u32,
+    /// Processes of level 2 and 3 under `process_id`, if any.
+    #[serde(default)]
+    pub sub_process_ids: Vec<u32>,
+    pub start_at: Option<String>,
+    pub end_at: Option<String>,
+    pub duration: u32,
+    #[serde(default)]
+    pub memo: String,
+}
+impl TimesheetInput {
+    fn body(&self) -> TimesheetBody<'_> {
+        TimesheetBody {
+            date: &self.date,
+            project_no: self.project_id,
+            process_1: self.process_id,
+            process_2: self.sub_process_ids.first().copied(),
+            process_3: self.sub_process_ids.get(1).copied(),
+            start_at: self.start_at.as_deref(),
+            end_at: self.end_at.as_deref(),
+            duration: self.duration,
+            memo: &self.memo,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct TimesheetBody<'a> {
+    date: &'a str,
+    project_no: u32,
+    process_1: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process_2: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process_3: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_at: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_at: Option<&'a str>,
+    duration: u32,
+    memo: &'a str,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct WorkContentResponse {
+    work_content: WorkContent,
+}
+
+/// Client of the Crowdlog API, for a single user.
+/// The session is kept in a cookie, and renewed with the credentials of the last login when it expires.
+pub struct ApiClient {
+    client: reqwest::Client,
+    base_url: String,
+    timezone: Tz,
+    // Credentials of the last successful login, used to re-login when the session expires.
+    credentials: Mutex<Credentials>,
+}
+impl ApiClient {
+    /// `base_url` is the URL of the tenant, e.g. `https://app.crowdlog.jp`. Nothing is sent until login.
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::ClientBuilder::new()
+            .cookie_store(true)
+            .referer(false)
+            .http2_keep_alive_interval(Duration::from_secs(60 * 50))
+            .build()
+            .unwrap();
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            timezone: DEFAULT_TIMEZONE,
+            credentials: Mutex::new(Credentials::new()),
+        }
+    }
+
+    /// Time zone of the tenant, in which Crowdlog gives and takes the dates and times. Japan time by default.
+    pub fn with_timezone(self, timezone: Tz) -> Self {
+        Self { timezone, ..self }
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    // Sends the request, and when the session has expired on their side (401),
+    // logs in again with the last successful credentials and replays the request once.
+    async fn send(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let retry = req.try_clone();
+        let res = req.send().await;
+
+        let is_expired = matches!(&res, Ok(v) if v.status() == reqwest::StatusCode::UNAUTHORIZED);
+        match retry {
+            Some(retry) if is_expired => {
+                if self.re_login().await.is_err() {
+                    return res;
+                }
+                retry.send().await
+            }
+            _ => res,
+        }
+    }
+
+    async fn re_login(&self) -> Result<(), ReqError> {
+        let cred = self.credentials.lock().unwrap().clone();
+        if !cred.is_valid() {
+            return Err(ReqError {
+                status: reqwest::StatusCode::UNAUTHORIZED.as_u16(),
+                message: String::from("Session expired and no credentials to re-login with"),
+                source: None,
+            });
+        }
+        self.login(&cred).await
+    }
+
+    /// Remembers the credentials without logging in, to login once the server is reachable again.
+    pub fn set_credentials(&self, cred: &Credentials) {
+        self.credentials.lock().unwrap().replace(cred);
+    }
+
+    /// Forgets the credentials, so that an expired session is not renewed anymore.
+    pub fn clear_credentials(&self) {
+        *self.credentials.lock().unwrap() = Credentials::new();
+    }
+
+    async fn adapt(&self, res: Result<reqwest::Response, reqwest::Error>) -> Result<(), ReqError> {
+        let response = log_if_error(res)?;
+
+        match response.error_for_status() {
+            Ok(_res) => Ok(()),
+            Err(err) => {
+                eprintln!("{:#?}", &err);
+                Err(ReqError::from(&err))
+            }
+        }
+    }
+
+    async fn adapt_json<T: serde::de::DeserializeOwned>(
+        &self,
+        res: Result<reqwest::Response, reqwest::Error>,
+    ) -> Result<T, ReqError> {
+        let response = log_if_error(res)?;
+
+        match response.error_for_status() {
+            Ok(res) => {
+                let body = log_if_error(res.json::<T>().await)?;
+                Ok(body)
+            }
+            Err(err) => {
+                eprintln!("{:#?}", &err);
+                Err(ReqError::from(&err))
+            }
+        }
+    }
+
+    /// Fails with a 400 on wrong credentials. The credentials are remembered on success.
+    pub async fn login(&self, cred: &Credentials) -> Result<(), ReqError> {
+        let url = self.url("login.cgi");
+        let email = cred.email.clone();
+        let passwd = cred.passwd.clone();
+
+        let form = multipart::Form::new()
+            .text("email", email)
+            .text("passwd", passwd)
+            .text("auto", "1")
+            .text("rm", "certify");
+
+        let req = self.client.post(&url).multipart(form).build().unwrap();
+        let res = self.client.execute(req).await;
+
+        let url = res
+            .as_ref()
+            .map_or_else(|_| None, |v| v.url().query().map(|x| x.to_string()));
+
+        if url == Some("result=invalid".to_string()) {
+            let err = ReqError {
+                status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+                message: String::from("Invalid email or password"),
+                source: None,
+            };
+            eprintln!("{:#?}", &err);
+            return Err(err);
+        };
+
+        self.adapt(res).await?;
+
+        self.credentials.lock().unwrap().replace(cred);
+        Ok(())
+    }
+
+    pub async fn get_user(&self) -> Result<User, ReqError> {
+        let url = self.url("data/page-header");
+
+        #[derive(Debug, serde::Deserialize)]
+        struct ServerUser {
+            user_id: u32,
+            email: String,
+            first_name: String,
+            family_name: String,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct Response {
+            user_info: ServerUser,
+        }
+
+        let res = self.send(self.client.get(url)).await;
+        let v: Response = self.adapt_json(res).await?;
+
+        Ok(User {
+            id: v.user_info.user_id,
+            email: v.user_info.email,
+            name: format!("{} {}", v.user_info.family_name, v.user_info.first_name),
+        })
+    }
+
+    pub async fn get_stop_watch(&self) -> Result<StopWatch, ReqError> {
+        let url = self.url("apis/my/stop_watches");
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Response {
+            stop_watches: Vec<StopWatch>,
+        }
+
+        let res = self.send(self.client.get(url)).await;
+        let v: Response = self.adapt_json(res).await?;
+
+        Ok(v.stop_watches[0].clone())
+    }
+
+    /// Recently used work contents.
+    pub async fn get_history(&self) -> Result<Vec<WorkContent>, ReqError> {
+        let url = self.url(
+            format!(
+                "apis/my/histories/work_contents?date={}&active=true",
+                today(self.timezone)
+            )
+            .as_str(),
+        );
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Response {
+            work_content_histories: Vec<WorkContentResponse>,
+        }
+        let res = self.send(self.client.get(url)).await;
+        let v: Response = self.adapt_json(res).await?;
+
+        let flattened = v
+            .work_content_histories
+            .iter()
+            .map(|x| x.work_content.clone())
+            .collect::<Vec<WorkContent>>();
+
+        Ok(flattened)
+    }
+
+    /// Work contents starred by the user.
+    pub async fn get_my_patterns(&self) -> Result<Vec<WorkContent>, ReqError> {
+        let url = self.url(
+            format!(
+                "apis/my/my_patterns/?date={}&active=true",
+                today(self.timezone)
+            )
+            .as_str(),
+        );
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Response {
+            my_patterns: Vec<WorkContentResponse>,
+        }
+
+        let res = self.send(self.client.get(url)).await;
+        let v: Response = self.adapt_json(res).await?;
+
+        let flattened = v
+            .my_patterns
+            .iter()
+            .map(|x| x.work_content.clone())
+            .collect::<Vec<WorkContent>>();
+
+        Ok(flattened)
+    }
+
+    /// Active projects of the user, of every page.
+    pub async fn get_projects(&self, user_id: u32) -> Result<Vec<Project>, ReqError> {
+        let mut projects = vec![];
+        self.stream_projects(user_id, |page, _| projects.extend(page))
+            .await?;
+        Ok(projects)
+    }
+
+    /// Calls `on_page` with every page as soon as it's received, and whether it's the last one.
+    pub async fn stream_projects(
+        &self,
+        user_id: u32,
+        on_page: impl FnMut(Vec<Project>, bool),
+    ) -> Result<(), ReqError> {
+        let url = self.url(
+            format!(
+                "apis/users/{user_id}/projects?date={}&active=true&per_page={PER_PAGE}",
+                today(self.timezone)
+            )
+            .as_str(),
+        );
+        self.get_pages(&url, on_page).await
+    }
+
+    /// Level 1 processes have no parent, deeper ones are listed under `parent_id`, a process of the level above.
+    pub async fn get_processes(
+        &self,
+        user_id: u32,
+        project_id: u32,
+        level: u8,
+        parent_id: Option<u32>,
+    ) -> Result<Vec<Process>, ReqError> {
+        let mut processes = vec![];
+        self.stream_processes(user_id, project_id, level, parent_id, |page, _| {
+            processes.extend(page)
+        })
+        .await?;
+        Ok(processes)
+    }
+
+    /// Calls `on_page` with every page as soon as it's received, and whether it's the last one.
+    pub async fn stream_processes(
+        &self,
+        user_id: u32,
+        project_id: u32,
+        level: u8,
+        parent_id: Option<u32>,
+        on_page: impl FnMut(Vec<Process>, bool),
+    ) -> Result<(), ReqError> {
+        if !(1..=MAX_PROCESS_LEVEL).contains(&level) || (level > 1) != parent_id.is_some() {
+            return Err(ReqError {
+                status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+                message: format!(
+                    "Invalid process level {} with parent {:?}",
+                    level, parent_id
+                ),
+                source: None,
+            });
+        }
+        let mut url = self.url(format!("apis/users/{user_id}/projects/{project_id}/processes/level/{level}/?active=true&per_page={PER_PAGE}").as_str());
+        if let Some(parent_id) = parent_id {
+            url.push_str(&format!("&parent_id={parent_id}"));
+        }
+        self.get_pages(&url, on_page).await
+    }
+
+    // Follows the pagination of a list, `url` must already have a query.
+    // Lists without pagination metadata are a single page.
+    async fn get_pages<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        mut on_page: impl FnMut(Vec<T>, bool),
+    ) -> Result<(), ReqError> {
+        #[derive(Debug, serde::Deserialize)]
+        struct Pagination {
+            current_page: u32,
+            total_pages: u32,
+        }
+        #[derive(Debug, serde::Deserialize)]
+        struct Response<T> {
+            #[serde(alias = "projects", alias = "processes")]
+            items: Vec<T>,
+            #[serde(default)]
+            pagination: Option<Pagination>,
+        }
+
+        let mut page = 1;
+        loop {
+            let req = self.client.get(format!("{}&page={}", url, page));
+            let res = self.send(req).await;
+            let v: Response<T> = self.adapt_json(res).await?;
+
+            // An empty page ends it too, so wrong metadata can't make it loop forever.
+            let is_last = v.items.is_empty()
+                || v.pagination
+                    .map_or(true, |p| p.current_page >= p.total_pages);
+            on_page(v.items, is_last);
+            if is_last {
+                return Ok(());
+            }
+            page += 1;
+        }
+    }
+
+    /// `process_ids` are from level 1 down, unused levels are sent as 0 to clear them.
+    /// The memo is sent along, as Crowdlog clears it when it's omitted.
+    pub async fn set_work_content(
+        &self,
+        sw_id: u32,
+        project_id: u32,
+        process_ids: &[u32],
+        memo: &str,
+    ) -> Result<WorkContent, ReqError> {
+        let url = self.url(format!("apis/my/stop_watches/{sw_id}/").as_str());
+
+        #[derive(Debug, serde::Serialize)]
+        struct Body<'a> {
+            project_no: u32,
+            process_1: u32,
+            process_2: u32,
+            process_3: u32,
+            memo: &'a str,
+        }
+        let level = |i: usize| process_ids.get(i).copied().unwrap_or(0);
+        let body = Body {
+            project_no: project_id,
+            process_1: level(0),
+            process_2: level(1),
+            process_3: level(2),
+            memo,
+        };
+
+        let res = self.send(self.client.put(url).json(&body)).await;
+        let v: WorkContentResponse = self.adapt_json(res).await?;
+
+        Ok(v.work_content)
+    }
+
+    /// Makes it the latest of the recently used ones, as the web app does when it's selected.
+    /// `process_ids` go from level 1 down to the deepest selected level.
+    pub async fn add_work_content_to_history(
+        &self,
+        project_id: u32,
+        process_ids: &[u32],
+    ) -> Result<(), ReqError> {
+        let url = self.url("apis/my/histories/work_contents/");
+
+        let level = |i: usize| process_ids.get(i).copied().unwrap_or(0);
+        let mut body = HashMap::new();
+        body.insert("project_id", project_id);
+        body.insert("process_id", level(0));
+        body.insert("process_2_id", level(1));
+        body.insert("process_3_id", level(2));
+
+        let res = self.send(self.client.put(url).json(&body)).await;
+        self.adapt(res).await?;
+
+        Ok(())
+    }
+
+    pub async fn start_timer(&self, sw_id: u32) -> Result<StopWatch, ReqError> {
+        let url = self.url(format!("apis/my/stop_watches/{sw_id}/start/").as_str());
+
+        let mut body = HashMap::new();
+        body.insert("ignore_id", sw_id);
+
+        let res = self.send(self.client.put(url).json(&body)).await;
+        let v: StopWatch = self.adapt_json(res).await?;
+
+        Ok(v)
+    }
+
+    /// The stopwatch needs to be applied to record the time, or reset to drop it.
+    pub async fn stop_timer(&self, sw_id: u32) -> Result<StopWatch, ReqError> {
+        let url = self.url(format!("apis/my/stop_watches/{sw_id}/stop/").as_str());
+
+        let res = self.send(self.client.put(url)).await;
+        let v: StopWatch = self.adapt_json(res).await?;
+
+        Ok(v)
+    }
+
+    /// Records the time of a stopped stopwatch as a timesheet entry, until its stop time.
+    /// To record it until another time, edit the entry afterwards, see [`ApiClient::find_applied_timesheet`].
+    pub async fn apply_timer(&self, sw_id: u32) -> Result<(), ReqError> {
+        let url = self.url("apis/my/timesheets/stop_watch_apply/");
+
+        let mut body = HashMap::new();
+        body.insert("id", sw_id);
+
+        let res = self.send(self.client.post(url).json(&body)).await;
+        self.adapt(res).await?;
+
+        Ok(())
+    }
+
+    /// The entry recorded by applying the `stopped` stopwatch, found by its start time and work content.
+    /// The latest one if several match, None if there is none.
+    pub async fn find_applied_timesheet(
+        &self,
+        stopped: &StopWatch,
+    ) -> Result<Option<Timesheet>, ReqError> {
+        let date = match stopped.start_at.get(..10) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let timesheets = self.get_timesheets(date, date).await?;
+        Ok(timesheets
+            .into_iter()
+            .filter(|v| {
+                v.start_at.as_deref() == Some(stopped.start_at.as_str())
+                    && v.work_content.ids() == stopped.work_content.ids()
+            })
+            .max_by_key(|v| v.id))
+    }
+
+    /// Clears the time of the stopwatch, keeping the work content.
+    pub async fn reset_timer(&self, sw_id: u32) -> Result<StopWatch, ReqError> {
+        let url = self.url(format!("apis/my/stop_watches/{sw_id}/reset/").as_str());
+
+        let res = self.send(self.client.put(url)).await;
+        let v: StopWatch = self.adapt_json(res).await?;
+
+        Ok(v)
+    }
+
+    /// Entries from `start_date` to `end_date`, both included. Format: `%Y-%m-%d`.
+    pub async fn get_timesheets(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<Timesheet>, ReqError> {
+        let url = self.url(
+            format!("apis/my/timesheets/?start_date={start_date}&end_date={end_date}").as_str(),
+        );
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Response {
+            timesheets: Vec<Timesheet>,
+        }
+
+        let res = self.send(self.client.get(url)).await;
+        let v: Response = self.adapt_json(res).await?;
+
+        Ok(v.timesheets)
+    }
+
+    pub async fn create_timesheet(&self, input: &TimesheetInput) -> Result<Timesheet, ReqError> {
+        let url = self.url("apis/my/timesheets/");
+
+        let res = self.send(self.client.post(url).json(&input.body())).await;
+        let v: TimesheetResponse = self.adapt_json(res).await?;
+
+        Ok(v.timesheet)
+    }
+
+    pub async fn edit_timesheet(
+        &self,
+        id: u32,
+        input: &TimesheetInput,
+    ) -> Result<Timesheet, ReqError> {
+        let url = self.url(format!("apis/my/timesheets/{id}/").as_str());
+
+        let res = self.send(self.client.put(url).json(&input.body())).await;
+        let v: TimesheetResponse = self.adapt_json(res).await?;
+
+        Ok(v.timesheet)
+    }
+
+    pub async fn delete_timesheet(&self, id: u32) -> Result<(), ReqError> {
+        let url = self.url(format!("apis/my/timesheets/{id}/").as_str());
+
+        let res = self.send(self.client.delete(url)).await;
+        self.adapt(res).await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TimesheetResponse {
+    timesheet: Timesheet,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{self, block_on, MockServer};
+
+    fn credentials(passwd: &str) -> Credentials {
+        Credentials {
+            email: String::from(mock_server::EMAIL),
+            passwd: String::from(passwd),
+        }
+    }
+
+    fn logged_in_client(server: &MockServer) -> ApiClient {
+        let api = ApiClient::new(server.base_url());
+        block_on(api.login(&credentials(mock_server::PASSWD))).unwrap();
+        api
+    }
+
+    #[test]
+    fn login_with_invalid_credentials() {
+        let server = MockServer::start();
+        let api = ApiClient::new(server.base_url());
+
+        let err = block_on(api.login(&credentials("wrong"))).unwrap_err();
+        assert_eq!(err.status, 400);
+        assert_eq!(server.state().login_count, 0);
+    }
+
+    #[test]
+    fn request_without_session_is_unauthorized() {
+        let server = MockServer::start();
+        let api = ApiClient::new(server.base_url());
+
+        let err = block_on(api.get_user()).unwrap_err();
+        assert_eq!(err.status, 401);
+    }
+
+    #[test]
+    fn re_login_when_session_expired() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+
+        server.expire_session();
+        let user = block_on(api.get_user()).unwrap();
+        assert_eq!(user.id, mock_server::USER_ID);
+        assert_eq!(server.state().login_count, 2);
+    }
+
+    #[test]
+    fn stop_watch_cycle() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+
+        let user = block_on(api.get_user()).unwrap();
+        assert_eq!(user.name, "Yamada Taro");
+        let projects = block_on(api.get_projects(user.id)).unwrap();
+        assert_eq!(projects.len(), 2);
+        let processes = block_on(api.get_processes(user.id, 1, 1, None)).unwrap();
+        assert_eq!(processes.len(), 2);
+
+        let sw = block_on(api.get_stop_watch()).unwrap();
+        assert!(matches!(sw.status, StopWatchStatus::Clean));
+
+        block_on(api.add_work_content_to_history(1, &[12, 122, 1221])).unwrap();
+        block_on(api.set_work_content(sw.id, 1, &[12], "Login form")).unwrap();
+        let history = block_on(api.get_history()).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].process_ids(), vec![12, 122, 1221]);
+        let sw = block_on(api.get_stop_watch()).unwrap();
+        assert_eq!(sw.work_content.memo(), "Login form");
+
+        let sw = block_on(api.start_timer(sw.id)).unwrap();
+        assert!(matches!(sw.status, StopWatchStatus::Started));
+        let sw = block_on(api.stop_timer(sw.id)).unwrap();
+        assert!(matches!(sw.status, StopWatchStatus::NeedToApply));
+        block_on(api.apply_timer(sw.id)).unwrap();
+        let applied = block_on(api.find_applied_timesheet(&sw)).unwrap().unwrap();
+        assert_eq!(applied.start_at, Some(sw.start_at.clone()));
+        assert_eq!(applied.end_at, sw.stop_at.clone());
+        let sw = block_on(api.reset_timer(sw.id)).unwrap();
+        assert!(matches!(sw.status, StopWatchStatus::Clean));
+
+        let today = crate::utils::today(DEFAULT_TIMEZONE);
+        let list = block_on(api.get_timesheets(&today, &today)).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].memo, "Login form");
+        assert_eq!(list[0].work_content.ids(), Some((1, 12)));
+        let input = list[0].to_input().unwrap();
+        assert_eq!((input.project_id, input.process_id), (1, 12));
+        assert_eq!(input.memo, "Login form");
+    }
+
+    #[test]
+    fn timesheet_cycle() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+
+        let input = TimesheetInput {
+            date: String::from("2024-07-01"),
+            project_id: 1,
+            process_id: 11,
+            sub_process_ids: vec![],
+            start_at: Some(String::from("2024-07-01 09:00:00")),
+            end_at: Some(String::from("2024-07-01 10:30:00")),
+            duration: 90,
+            memo: String::from("Wireframes"),
+        };
+        let created = block_on(api.create_timesheet(&input)).unwrap();
+        assert_eq!(created.work_content.names(), Some(("Acme Corp", "Design")));
+        block_on(api.create_timesheet(&TimesheetInput {
+            date: String::from("2024-07-02"),
+            ..input.clone()
+        }))
+        .unwrap();
+
+        let list = block_on(api.get_timesheets("2024-07-01", "2024-07-01")).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].memo, "Wireframes");
+
+        let edited = block_on(api.edit_timesheet(
+            created.id,
+            &TimesheetInput {
+                process_id: 12,
+                duration: 60,
+                ..input.clone()
+            },
+        ))
+        .unwrap();
+        assert_eq!(edited.duration, 60);
+        assert_eq!(edited.work_content.ids(), Some((1, 12)));
+        assert_eq!(edited.memo, "Wireframes");
+
+        // Deeper levels are recorded and kept on edit
+        let deep = TimesheetInput {
+            process_id: 12,
+            sub_process_ids: vec![122, 1221],
+            ..input.clone()
+        };
+        let edited = block_on(api.edit_timesheet(created.id, &deep)).unwrap();
+        assert_eq!(edited.work_content.process_ids(), vec![12, 122, 1221]);
+
+        block_on(api.delete_timesheet(created.id)).unwrap();
+        let list = block_on(api.get_timesheets("2024-07-01", "2024-07-02")).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].date, "2024-07-02");
+    }
+
+    #[test]
+    fn process_tree() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+
+        let level_2 = block_on(api.get_processes(mock_server::USER_ID, 1, 2, Some(12))).unwrap();
+        let names: Vec<&str> = level_2.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["Frontend", "Backend"]);
+        let level_3 = block_on(api.get_processes(mock_server::USER_ID, 1, 3, Some(122))).unwrap();
+        assert_eq!(level_3.len(), 1);
+        assert!(block_on(api.get_processes(mock_server::USER_ID, 1, 2, None)).is_err());
+
+        let sw = block_on(api.get_stop_watch()).unwrap();
+        let wc = block_on(api.set_work_content(sw.id, 1, &[12, 122, level_3[0].id], "")).unwrap();
+        assert_eq!(wc.process_ids(), vec![12, 122, level_3[0].id]);
+
+        // Deeper levels are cleared when only level 1 is set
+        let wc = block_on(api.set_work_content(sw.id, 1, &[11], "")).unwrap();
+        assert_eq!(wc.process_ids(), vec![11]);
+    }
+
+    #[test]
+    fn follow_pagination() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+
+        let processes = block_on(api.get_processes(mock_server::USER_ID, 3, 1, None)).unwrap();
+        assert_eq!(processes.len(), mock_server::MANY_PROCESSES as usize);
+        assert_eq!(processes.last().unwrap().name, "Task 230");
+
+        let mut pages = vec![];
+        block_on(
+            api.stream_processes(mock_server::USER_ID, 3, 1, None, |page, is_last| {
+                pages.push((page.len(), is_last))
+            }),
+        )
+        .unwrap();
+        assert_eq!(pages, vec![(100, false), (100, false), (30, true)]);
+    }
+}
@@ -0,0 +1,42 @@
+//! Client of [Crowdlog](https://crowdlog.io/), the time tracking service, for its stopwatch and timesheets.
+//!
+//! It's the client used by the CrowdDog desktop app, without anything of the app,
+//! so that other tools can drive Crowdlog too.
+//!
+//! - [`ApiClient`] sends the requests. Its methods are async, run them on a tokio runtime.
+//! - [`Settings`] reads and writes a settings file with the credentials,
+//!   e.g. to reuse the login of the desktop app.
+//! - Dates and times are naive ones in the time zone of the tenant, see [`ApiClient::with_timezone`].
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), crowdlog::ReqError> {
+//! use crowdlog::{ApiClient, Credentials, StopWatchStatus};
+//!
+//! let api = ApiClient::new("https://app.crowdlog.jp");
+//! api.login(&Credentials {
+//!     email: String::from("taro@example.com"),
+//!     passwd: String::from("secret"),
+//! })
+//! .await?;
+//!
+//! let sw = api.get_stop_watch().await?;
+//! if matches!(sw.status, StopWatchStatus::Started) {
+//!     let stopped = api.stop_timer(sw.id).await?;
+//!     api.apply_timer(stopped.id).await?;
+//!     api.reset_timer(stopped.id).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod api;
+#[cfg(any(test, feature = "mock-server"))]
+#[doc(hidden)]
+pub mod mock_server;
+mod secret;
+pub mod settings;
+pub mod utils;
+
+pub use api::{ApiClient, ReqError, StopWatch, StopWatchStatus, WorkContent};
+pub use chrono_tz::Tz;
+pub use settings::{Credentials, Settings};
@@ -0,0 +1,516 @@
+// In-process imitation of the Crowdlog server, so that `ApiClient` can be exercised without network access.
+// It speaks just enough HTTP/1.1 for reqwest, and closes the connection after every response.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const EMAIL: &str = "taro@example.com";
+pub const PASSWD: &str = "correct horse battery staple";
+pub const USER_ID: u32 = 7;
+pub const STOP_WATCH_ID: u32 = 42;
+// Level 1 processes of project 3, more than a page.
+pub const MANY_PROCESSES: u32 = 230;
+
+const SESSION_COOKIE: &str = "crowdlog_session";
+
+pub struct MockState {
+    pub session: Option<String>,
+    pub login_count: u32,
+    pub stop_watch: Value,
+    pub history: Vec<Value>,
+    pub my_patterns: Vec<Value>,
+    pub timesheets: Vec<Value>,
+    next_timesheet_id: u32,
+    // Method and path of the next request to fail.
+    failing: Option<(String, String)>,
+}
+impl MockState {
+    fn new() -> Self {
+        Self {
+            session: None,
+            login_count: 0,
+            stop_watch: json!({
+                "id": STOP_WATCH_ID,
+                "start_at": "",
+                "stop_at": null,
+                "status": "reset",
+                "work_content": empty_work_content(),
+            }),
+            history: vec![],
+            my_patterns: vec![json!({ "work_content": work_content(1, 11) })],
+            timesheets: vec![],
+            next_timesheet_id: 1,
+            failing: None,
+        }
+    }
+}
+
+pub struct MockServer {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::new()));
+
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&shared);
+                thread::spawn(move || handle_connection(stream, &state));
+            }
+        });
+
+        Self { base_url, state }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    // Drops the session on the server side, as Crowdlog does after a while.
+    pub fn expire_session(&self) {
+        self.state().session = None;
+    }
+
+    // Answers the next request to `path` with a server error, then serves it again.
+    pub fn fail_once(&self, method: &str, path: &str) {
+        self.state().failing = Some((method.to_string(), path.trim_matches('/').to_string()));
+    }
+}
+
+// Runs a request of `ApiClient` to completion, the crate has no runtime of its own.
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Unable to build a runtime")
+        .block_on(future)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+impl Request {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    fn cookie(&self, name: &str) -> Option<String> {
+        self.headers.get("cookie").and_then(|v| {
+            v.split(';')
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", String::from("application/json"))],
+            body: body.to_string(),
+        }
+    }
+
+    fn redirect(location: &str) -> Self {
+        Self {
+            status: 302,
+            headers: vec![("Location", location.to_string())],
+            body: String::new(),
+        }
+    }
+
+    fn html() -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type", String::from("text/html"))],
+            body: String::from("<html></html>"),
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<MockState>) {
+    let req = match read_request(&stream) {
+        Some(req) => req,
+        None => return,
+    };
+    let res = route(&req, &mut state.lock().unwrap());
+
+    let mut head = format!("HTTP/1.1 {} MOCK\r\n", res.status);
+    for (k, v) in res.headers.iter() {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        res.body.len()
+    ));
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(res.body.as_bytes());
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.insert(k.trim().to_lowercase(), v.trim().to_string());
+        }
+    }
+
+    let len = headers
+        .get("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn route(req: &Request, state: &mut MockState) -> Response {
+    let (path, query) = req.path.split_once('?').unwrap_or((&req.path, ""));
+    let segments: Vec<&str> = path.split('/').filter(|v| !v.is_empty()).collect();
+
+    if segments == ["login.cgi"] {
+        return match req.method.as_str() {
+            "POST" => login(req, state),
+            _ => Response::html(),
+        };
+    }
+    if segments == ["home"] {
+        return Response::html();
+    }
+
+    let is_authorized = state.session.is_some() && req.cookie(SESSION_COOKIE) == state.session;
+    if !is_authorized {
+        return Response::json(401, json!({ "message": "Unauthorized" }));
+    }
+
+    let is_failing = state.failing.as_ref().map_or(false, |(method, path)| {
+        *method == req.method && *path == segments.join("/")
+    });
+    if is_failing {
+        state.failing = None;
+        return Response::json(500, json!({ "message": "Internal Server Error" }));
+    }
+
+    match (req.method.as_str(), segments.as_slice()) {
+        ("GET", ["data", "page-header"]) => Response::json(
+            200,
+            json!({
+                "user_info": {
+                    "user_id": USER_ID,
+                    "email": EMAIL,
+                    "first_name": "Taro",
+                    "family_name": "Yamada",
+                }
+            }),
+        ),
+        ("GET", ["apis", "my", "stop_watches"]) => {
+            Response::json(200, json!({ "stop_watches": [state.stop_watch] }))
+        }
+        ("PUT", ["apis", "my", "stop_watches", _id]) => {
+            let body = req.json();
+            let mut wc = requested_work_content(&body);
+            wc["memo"] = json!(body["memo"].as_str().unwrap_or(""));
+            state.stop_watch["work_content"] = wc.clone();
+            Response::json(200, json!({ "work_content": wc }))
+        }
+        ("PUT", ["apis", "my", "stop_watches", _id, action]) => {
+            match *action {
+                "start" => {
+                    state.stop_watch["status"] = json!("timing");
+                    state.stop_watch["start_at"] = json!(now());
+                }
+                "stop" => {
+                    state.stop_watch["status"] = json!("stopped");
+                    state.stop_watch["stop_at"] = json!(now());
+                }
+                "reset" => {
+                    state.stop_watch["status"] = json!("reset");
+                    state.stop_watch["start_at"] = json!("");
+                    state.stop_watch["stop_at"] = Value::Null;
+                }
+                _ => return Response::json(404, json!({})),
+            }
+            Response::json(200, state.stop_watch.clone())
+        }
+        ("POST", ["apis", "my", "timesheets", "stop_watch_apply"]) => {
+            if state.stop_watch["status"] != "stopped" {
+                return Response::json(400, json!({ "message": "Stop watch is not stopped" }));
+            }
+            // Recorded until the stop time, whatever else is sent.
+            let end_at = state.stop_watch["stop_at"].clone();
+            let start_at = state.stop_watch["start_at"]
+                .as_str()
+                .unwrap_or("")
+                .to_string();
+            let end_at = end_at.as_str().unwrap_or("").to_string();
+            let entry = json!({
+                "id": state.next_timesheet_id,
+                "date": start_at.get(..10).unwrap_or(""),
+                "start_at": start_at,
+                "end_at": end_at,
+                "duration": minutes_between(&start_at, &end_at),
+                "memo": state.stop_watch["work_content"]["memo"].as_str().unwrap_or(""),
+                "work_content": state.stop_watch["work_content"],
+            });
+            state.next_timesheet_id += 1;
+            state.timesheets.push(entry);
+            Response::json(200, json!({}))
+        }
+        ("GET", ["apis", "my", "timesheets"]) => {
+            let start_date = query_param(query, "start_date").unwrap_or_default();
+            let end_date = query_param(query, "end_date").unwrap_or_default();
+            let timesheets: Vec<&Value> = state
+                .timesheets
+                .iter()
+                .filter(|v| {
+                    let date = v["date"].as_str().unwrap_or("");
+                    start_date.as_str() <= date && date <= end_date.as_str()
+                })
+                .collect();
+            Response::json(200, json!({ "timesheets": timesheets }))
+        }
+        ("POST", ["apis", "my", "timesheets"]) => {
+            let mut entry = timesheet(&req.json());
+            entry["id"] = json!(state.next_timesheet_id);
+            state.next_timesheet_id += 1;
+            state.timesheets.push(entry.clone());
+            Response::json(200, json!({ "timesheet": entry }))
+        }
+        ("PUT", ["apis", "my", "timesheets", id]) => {
+            let id: u32 = id.parse().unwrap_or(0);
+            match state.timesheets.iter_mut().find(|v| v["id"] == id) {
+                Some(entry) => {
+                    *entry = timesheet(&req.json());
+                    entry["id"] = json!(id);
+                    Response::json(200, json!({ "timesheet": entry }))
+                }
+                None => Response::json(404, json!({ "message": "Not Found" })),
+            }
+        }
+        ("DELETE", ["apis", "my", "timesheets", id]) => {
+            let id: u32 = id.parse().unwrap_or(0);
+            let len = state.timesheets.len();
+            state.timesheets.retain(|v| v["id"] != id);
+            if state.timesheets.len() == len {
+                return Response::json(404, json!({ "message": "Not Found" }));
+            }
+            Response::json(200, json!({}))
+        }
+        ("GET", ["apis", "my", "histories", "work_contents"]) => {
+            Response::json(200, json!({ "work_content_histories": state.history }))
+        }
+        ("PUT", ["apis", "my", "histories", "work_contents"]) => {
+            let body = req.json();
+            let mut wc = work_content(
+                body["project_id"].as_u64().unwrap_or(0) as u32,
+                body["process_id"].as_u64().unwrap_or(0) as u32,
+            );
+            for (level, key) in [("process_2", "process_2_id"), ("process_3", "process_3_id")] {
+                if let Some(id) = body[key].as_u64().filter(|v| *v > 0) {
+                    wc[level] = process(id as u32);
+                }
+            }
+            state.history.retain(|v| v["work_content"] != wc);
+            state.history.insert(0, json!({ "work_content": wc }));
+            Response::json(200, json!({}))
+        }
+        ("GET", ["apis", "my", "my_patterns"]) => {
+            Response::json(200, json!({ "my_patterns": state.my_patterns }))
+        }
+        ("GET", ["apis", "users", _user_id, "projects"]) => {
+            let projects: Vec<Value> = PROJECTS.iter().map(|(id, _)| project(*id)).collect();
+            Response::json(200, paginate("projects", projects, query))
+        }
+        (
+            "GET",
+            ["apis", "users", _user_id, "projects", project_id, "processes", "level", level],
+        ) => {
+            let project_id: u32 = project_id.parse().unwrap_or(0);
+            let level: u8 = level.parse().unwrap_or(0);
+            let parent_id: u32 = query_param(query, "parent_id")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            let processes: Vec<Value> = if project_id == 3 && level == 1 {
+                (1..=MANY_PROCESSES)
+                    .map(|i| json!({ "id": 3000 + i, "name": format!("Task {}", i) }))
+                    .collect()
+            } else {
+                PROCESSES
+                    .iter()
+                    .filter(|v| v.2 == project_id && v.3 == level && v.4 == parent_id)
+                    .map(|(id, ..)| process(*id))
+                    .collect()
+            };
+            Response::json(200, paginate("processes", processes, query))
+        }
+        _ => {
+            eprintln!("mock server: unhandled {} {}?{}", req.method, path, query);
+            Response::json(404, json!({ "message": "Not Found" }))
+        }
+    }
+}
+
+fn login(req: &Request, state: &mut MockState) -> Response {
+    let has_field = |name: &str, value: &str| {
+        req.body
+            .contains(&format!("name=\"{}\"\r\n\r\n{}\r\n", name, value))
+    };
+    if !has_field("email", EMAIL) || !has_field("passwd", PASSWD) {
+        return Response::redirect("/login.cgi?result=invalid");
+    }
+
+    state.login_count += 1;
+    let session = format!("session-{}", state.login_count);
+    state.session = Some(session.clone());
+
+    let mut res = Response::redirect("/home");
+    res.headers.push((
+        "Set-Cookie",
+        format!("{}={}; Path=/; HttpOnly", SESSION_COOKIE, session),
+    ));
+    res
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+// A page of the list, 1-based as requested by `page` and `per_page`.
+fn paginate(key: &str, items: Vec<Value>, query: &str) -> Value {
+    let param = |name: &str, default: usize| {
+        query_param(query, name)
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(default)
+    };
+    let page = param("page", 1);
+    let per_page = param("per_page", 20);
+    let total_pages = ((items.len() + per_page - 1) / per_page).max(1);
+
+    let page_items: Vec<Value> = items
+        .into_iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+    json!({
+        key: page_items,
+        "pagination": { "current_page": page, "total_pages": total_pages },
+    })
+}
+
+// Timesheet entry from a create or edit request, without its id.
+fn timesheet(body: &Value) -> Value {
+    json!({
+        "date": body["date"],
+        "start_at": body["start_at"],
+        "end_at": body["end_at"],
+        "duration": body["duration"],
+        "memo": body["memo"],
+        "work_content": requested_work_content(body),
+    })
+}
+
+// Work content of a request body, down to the deepest process level that is set.
+fn requested_work_content(body: &Value) -> Value {
+    let mut wc = work_content(
+        body["project_no"].as_u64().unwrap_or(0) as u32,
+        body["process_1"].as_u64().unwrap_or(0) as u32,
+    );
+    for level in ["process_2", "process_3"] {
+        if let Some(id) = body[level].as_u64().filter(|v| *v > 0) {
+            wc[level] = process(id as u32);
+        }
+    }
+    wc
+}
+
+fn minutes_between(start_at: &str, end_at: &str) -> i64 {
+    let parse = |v: &str| chrono::NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S").ok();
+    match (parse(start_at), parse(end_at)) {
+        (Some(start), Some(end)) => end.signed_duration_since(start).num_minutes(),
+        _ => 0,
+    }
+}
+
+fn now() -> String {
+    crate::utils::now(crate::utils::DEFAULT_TIMEZONE)
+}
+
+const PROJECTS: [(u32, &str); 2] = [(1, "Acme Corp"), (2, "Globex")];
+// (id, name, project id, level, parent process id)
+const PROCESSES: [(u32, &str, u32, u8, u32); 7] = [
+    (11, "Design", 1, 1, 0),
+    (12, "Development", 1, 1, 0),
+    (121, "Frontend", 1, 2, 12),
+    (122, "Backend", 1, 2, 12),
+    (1221, "API", 1, 3, 122),
+    (21, "Meeting", 2, 1, 0),
+    (22, "Support", 2, 1, 0),
+];
+
+fn project(id: u32) -> Value {
+    let name = PROJECTS
+        .iter()
+        .find(|(v, _)| *v == id)
+        .map_or("", |(_, name)| name);
+    json!({ "id": id, "name": name })
+}
+
+fn process(id: u32) -> Value {
+    let name = PROCESSES.iter().find(|v| v.0 == id).map_or("", |v| v.1);
+    json!({ "id": id, "name": name })
+}
+
+fn work_content(project_id: u32, process_id: u32) -> Value {
+    json!({ "project": project(project_id), "process": process(process_id) })
+}
+
+fn empty_work_content() -> Value {
+    work_content(0, 0)
+}
@@ -0,0 +1,183 @@
+// Storage of the Crowdlog password.
+// The platform secret store (Secret Service on Linux, Keychain, Credential Manager) is used when available,
+// otherwise the password is encrypted with AES-256-GCM using a random key kept in a machine-local file.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::{fs, io};
+
+const SERVICE: &str = "com.oliancho.crowddog";
+pub(crate) const KEY_FILE: &str = "secret.key";
+const NONCE_LEN: usize = 12;
+
+// Where the password has been stored. Written in the settings file instead of the password itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "store", rename_all = "snake_case")]
+pub enum StoredSecret {
+    Keyring,
+    Encrypted { data: String },
+}
+
+pub fn store(dir: &Path, account: &str, passwd: &str) -> Result<StoredSecret, io::Error> {
+    match keyring::Entry::new(SERVICE, account).and_then(|v| v.set_password(passwd)) {
+        Ok(()) => return Ok(StoredSecret::Keyring),
+        Err(e) => eprintln!(
+            "Keyring is not available, fallback to an encrypted file: {}",
+            e
+        ),
+    }
+    store_encrypted(dir, account, passwd)
+}
+
+pub(crate) fn store_encrypted(
+    dir: &Path,
+    account: &str,
+    passwd: &str,
+) -> Result<StoredSecret, io::Error> {
+    let key = load_or_create_key(dir)?;
+    let data = encrypt(&key, account, passwd)?;
+    Ok(StoredSecret::Encrypted { data })
+}
+
+pub fn load(dir: &Path, account: &str, stored: &StoredSecret) -> Result<String, io::Error> {
+    match stored {
+        StoredSecret::Keyring => keyring::Entry::new(SERVICE, account)
+            .and_then(|v| v.get_password())
+            .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e)),
+        StoredSecret::Encrypted { data } => {
+            let key = load_or_create_key(dir)?;
+            decrypt(&key, account, data)
+        }
+    }
+}
+
+pub fn delete(account: &str, stored: &StoredSecret) {
+    if *stored != StoredSecret::Keyring {
+        return;
+    }
+    if let Err(e) = keyring::Entry::new(SERVICE, account).and_then(|v| v.delete_password()) {
+        eprintln!("Unable to delete the password from keyring: {}", e);
+    }
+}
+
+// Decodes the shifted-byte format written by older versions, for migration only.
+pub fn decode_legacy(passwd: &str) -> Option<String> {
+    let vec: Vec<u8> = passwd.bytes().map(|x| x.wrapping_sub(1)).collect();
+    String::from_utf8(vec).ok()
+}
+
+fn load_or_create_key(dir: &Path) -> Result<Key<Aes256Gcm>, io::Error> {
+    let path = dir.join(KEY_FILE);
+    if let Ok(bytes) = fs::read(&path) {
+        if bytes.len() == 32 {
+            return Ok(*Key::<Aes256Gcm>::from_slice(&bytes));
+        }
+        eprintln!("Invalid key file, regenerating. path: {:#?}", path);
+    }
+
+    fs::create_dir_all(dir)?;
+    let key = Aes256Gcm::generate_key(OsRng);
+    write_private_file(&path, key.as_slice())?;
+    Ok(key)
+}
+
+#[cfg(unix)]
+fn write_private_file(path: &Path, data: &[u8]) -> Result<(), io::Error> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(data)
+}
+
+#[cfg(not(unix))]
+fn write_private_file(path: &Path, data: &[u8]) -> Result<(), io::Error> {
+    fs::write(path, data)
+}
+
+// The account is bound as associated data, so a ciphertext can't be replayed for another account.
+fn encrypt(key: &Key<Aes256Gcm>, account: &str, passwd: &str) -> Result<String, io::Error> {
+    let cipher = Aes256Gcm::new(key);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: passwd.as_bytes(),
+        aad: account.as_bytes(),
+    };
+    let encrypted = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Unable to encrypt the password"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend(encrypted);
+    Ok(BASE64.encode(data))
+}
+
+fn decrypt(key: &Key<Aes256Gcm>, account: &str, data: &str) -> Result<String, io::Error> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let data = BASE64
+        .decode(data)
+        .map_err(|_| invalid("Encrypted password is not valid base64"))?;
+    if data.len() < NONCE_LEN {
+        return Err(invalid("Encrypted password is too short"));
+    }
+    let (nonce, encrypted) = data.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(key);
+    let payload = Payload {
+        msg: encrypted,
+        aad: account.as_bytes(),
+    };
+    let decrypted = cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| invalid("Unable to decrypt the password"))?;
+    String::from_utf8(decrypted).map_err(|_| invalid("Decrypted password is not valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_str(str: &str) {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let encrypted = encrypt(&key, "taro@example.com", str).unwrap();
+        assert_ne!(str, encrypted);
+        let decrypted = decrypt(&key, "taro@example.com", &encrypted).unwrap();
+        assert_eq!(str, decrypted);
+    }
+
+    #[test]
+    fn encrypt_decrypt_utf8() {
+        test_str("password");
+        test_str("Hy3u45!ZRdLz*Kp#2B^DyRMs$irXCZtD");
+        test_str("🎉emoji_is_supported!!");
+    }
+
+    #[test]
+    fn decrypt_rejects_other_account_or_key() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let encrypted = encrypt(&key, "taro@example.com", "password").unwrap();
+
+        assert!(decrypt(&key, "hanako@example.com", &encrypted).is_err());
+        let other_key = Aes256Gcm::generate_key(OsRng);
+        assert!(decrypt(&other_key, "taro@example.com", &encrypted).is_err());
+    }
+
+    #[test]
+    fn decode_legacy_format() {
+        // "password" shifted by one byte, as written by older versions
+        assert_eq!(decode_legacy("qbttxpse").as_deref(), Some("password"));
+        assert_eq!(
+            decode_legacy("Iz4v56\"[SeM{+Lq$3C_EzSNt%jsYD[uE").as_deref(),
+            Some("Hy3u45!ZRdLz*Kp#2B^DyRMs$irXCZtD")
+        );
+    }
+}
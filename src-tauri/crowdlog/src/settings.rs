@@ -0,0 +1,260 @@
+// Settings file holding the credentials, and the preferences of whoever uses the client.
+
+use crate::secret::{self, StoredSecret};
+use crate::utils::DEFAULT_TIMEZONE;
+use chrono_tz::Tz;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::{env, fs, io};
+
+const DEFAULT_BASE_URL: &str = "https://app.crowdlog.jp";
+// Takes precedence over the `base_url` in the settings file, e.g. to point a dev build at a staging tenant.
+const BASE_URL_ENV: &str = "CROWDLOG_BASE_URL";
+
+/// Email and password to login to Crowdlog with.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Credentials {
+    pub email: String,
+    pub passwd: String,
+}
+impl Credentials {
+    pub fn new() -> Self {
+        Self {
+            email: String::new(),
+            passwd: String::new(),
+        }
+    }
+
+    /// Copies both fields from `new_settings`.
+    pub fn replace(&mut self, new_settings: &Credentials) {
+        self.email = new_settings.email.clone();
+        self.passwd = new_settings.passwd.clone();
+    }
+
+    /// True when both are filled in, which does not mean they are correct.
+    pub fn is_valid(&self) -> bool {
+        !self.email.is_empty() && !self.passwd.is_empty()
+    }
+}
+
+// How the credentials are written in `settings.json`. The password itself lives in the secret store.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct StoredCredentials {
+    #[serde(default)]
+    email: String,
+    // Shifted-byte password written by older versions. Only read to migrate it to the secret store.
+    #[serde(default, rename = "passwd", skip_serializing)]
+    legacy_passwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret: Option<StoredSecret>,
+}
+
+/// Content of a settings file, e.g. the `settings.json` of the desktop app.
+/// `P` is the rest of the file, written at the top level next to the credentials.
+/// The password is kept in the OS secret store, or encrypted in the file when there is none.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Settings<P> {
+    #[serde(skip)]
+    pub credentials: Credentials,
+    #[serde(flatten)]
+    stored_credentials: StoredCredentials,
+    // What `stored_credentials` holds, None if it couldn't be restored, e.g. while the keyring is locked.
+    #[serde(skip)]
+    saved_credentials: Option<Credentials>,
+    /// URL of the tenant, see `base_url()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Time zone of the tenant, an IANA name like `Asia/Tokyo`, see `timezone()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(flatten)]
+    pub preferences: P,
+}
+impl<P: Serialize + DeserializeOwned + Default> Settings<P> {
+    pub fn new() -> Self {
+        Self {
+            credentials: Credentials::new(),
+            stored_credentials: StoredCredentials::default(),
+            saved_credentials: Some(Credentials::new()),
+            base_url: None,
+            timezone: None,
+            preferences: P::default(),
+        }
+    }
+
+    /// Same as `load()`, then rewrites the file if the password was saved in the format of older versions.
+    pub fn init(file_path: &Path) -> Self {
+        let mut settings = Self::load(file_path);
+        if settings.stored_credentials.legacy_passwd.is_some() {
+            // Migrate the old format once, rewriting the file without the password.
+            settings.save(file_path);
+        }
+        settings
+    }
+
+    /// Reads the file and restores the password, without writing anything back.
+    /// Falls back to the defaults when the file is missing or broken.
+    pub fn load(file_path: &Path) -> Self {
+        let mut settings: Self = match restore_from_file(file_path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{}, path: {:#?}", e, file_path);
+                return Self::new();
+            }
+        };
+
+        let stored = &settings.stored_credentials;
+        if stored.secret.is_none() && stored.legacy_passwd.is_none() {
+            settings.saved_credentials = Some(Credentials::new());
+        }
+        if let Some(legacy) = stored.legacy_passwd.as_deref() {
+            settings.credentials = Credentials {
+                email: stored.email.clone(),
+                passwd: secret::decode_legacy(legacy).unwrap_or_default(),
+            };
+        } else if let Some(stored_secret) = stored.secret.as_ref() {
+            let dir = file_path.parent().unwrap();
+            match secret::load(dir, &stored.email, stored_secret) {
+                Ok(passwd) => {
+                    settings.credentials = Credentials {
+                        email: stored.email.clone(),
+                        passwd,
+                    };
+                    settings.saved_credentials = Some(settings.credentials.clone());
+                }
+                Err(e) => eprintln!("Unable to restore the password: {}", e),
+            }
+        }
+
+        settings
+    }
+
+    /// Writes the file, and the password to the secret store when the credentials have changed.
+    pub fn save(&mut self, file_path: &Path) {
+        let is_changed = match self.saved_credentials.as_ref() {
+            Some(saved) => *saved != self.credentials,
+            // Only a new login replaces the credentials that couldn't be restored.
+            None => self.credentials.is_valid(),
+        };
+        if is_changed {
+            self.store_credentials(file_path.parent().unwrap());
+        }
+
+        write_file(file_path, self);
+    }
+
+    fn store_credentials(&mut self, dir: &Path) {
+        let mut stored = StoredCredentials {
+            email: self.credentials.email.clone(),
+            legacy_passwd: None,
+            secret: None,
+        };
+        self.saved_credentials = Some(self.credentials.clone());
+        if self.credentials.is_valid() {
+            match secret::store(dir, &stored.email, &self.credentials.passwd) {
+                Ok(v) => stored.secret = Some(v),
+                Err(e) => {
+                    eprintln!("Unable to store the password: {}", e);
+                    self.saved_credentials = None;
+                }
+            }
+        }
+        self.stored_credentials = stored;
+    }
+
+    /// Forgets the credentials but keeps the rest of the settings.
+    pub fn clear_credentials(&mut self, file_path: &Path) {
+        if let Some(stored_secret) = self.stored_credentials.secret.as_ref() {
+            secret::delete(&self.stored_credentials.email, stored_secret);
+        }
+        self.credentials = Credentials::new();
+        self.save(file_path);
+    }
+
+    /// The `CROWDLOG_BASE_URL` environment variable, the one in the file, or the default tenant.
+    pub fn base_url(&self) -> String {
+        if let Ok(url) = env::var(BASE_URL_ENV) {
+            if !url.is_empty() {
+                return url;
+            }
+        }
+        self.base_url
+            .clone()
+            .unwrap_or_else(|| String::from(DEFAULT_BASE_URL))
+    }
+
+    /// The time zone in the file, or the one of the default tenant when it's not set or unknown.
+    pub fn timezone(&self) -> Tz {
+        match self.timezone.as_deref().map(str::parse::<Tz>) {
+            Some(Ok(tz)) => tz,
+            Some(Err(e)) => {
+                eprintln!("Unknown time zone in the settings: {}", e);
+                DEFAULT_TIMEZONE
+            }
+            None => DEFAULT_TIMEZONE,
+        }
+    }
+}
+
+fn write_file<T: Serialize>(path: &Path, data: &T) {
+    let dir = path.parent().unwrap();
+    if !dir.exists() {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let data = serde_json::to_string(data).expect("Unable to serialize JSON");
+    fs::write(path, data).expect("Unable to write file");
+}
+
+fn restore_from_file<T: DeserializeOwned>(path: &Path) -> Result<T, io::Error> {
+    let json = fs::read_to_string(path)?;
+    let settings: T = serde_json::from_str(&json)?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Preferences {
+        #[serde(default)]
+        theme: String,
+    }
+
+    fn temp_dir() -> std::path::PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        env::temp_dir().join(format!("crowdlog-settings-{}", nanos))
+    }
+
+    #[test]
+    fn keep_credentials_that_could_not_be_restored() {
+        let dir = temp_dir();
+        let path = dir.join("settings.json");
+        let key_path = dir.join(secret::KEY_FILE);
+        let email = "taro@example.com";
+        let stored = secret::store_encrypted(&dir, email, "password").unwrap();
+        let key = fs::read(&key_path).unwrap();
+        let json = serde_json::json!({ "email": email, "secret": stored, "theme": "dark" });
+        fs::write(&path, json.to_string()).unwrap();
+
+        // The key is unavailable at startup, so the password can't be decrypted.
+        fs::remove_file(&key_path).unwrap();
+        let mut settings = Settings::<Preferences>::load(&path);
+        assert!(!settings.credentials.is_valid());
+        settings.preferences.theme = String::from("light");
+        settings.save(&path);
+
+        fs::write(&key_path, key).unwrap();
+        let settings = Settings::<Preferences>::load(&path);
+        assert_eq!(settings.credentials.email, email);
+        assert_eq!(settings.credentials.passwd, "password");
+        assert_eq!(settings.preferences.theme, "light");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use crate::api::ReqError;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Format of the date times sent and received by Crowdlog.
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Format of the dates sent and received by Crowdlog.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Time zone of the tenant unless set otherwise. Crowdlog gives the times of Japan without any offset.
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::Asia::Tokyo;
+
+/// Today in the time zone of the tenant, in `DATE_FORMAT`.
+pub fn today(tz: Tz) -> String {
+    tenant_time(Utc::now(), tz).format(DATE_FORMAT).to_string()
+}
+
+/// Now in the time zone of the tenant, in `TIME_FORMAT`.
+pub fn now(tz: Tz) -> String {
+    tenant_time(Utc::now(), tz).format(TIME_FORMAT).to_string()
+}
+
+/// The date time given by Crowdlog for `at`, whatever the time zone of this machine is.
+pub fn tenant_time(at: DateTime<Utc>, tz: Tz) -> NaiveDateTime {
+    at.with_timezone(&tz).naive_local()
+}
+
+/// The instant of a date time given by Crowdlog, None if it doesn't exist in the tenant's time zone,
+/// i.e. skipped by daylight saving time. The earlier one when it happens twice.
+pub fn instant(time: NaiveDateTime, tz: Tz) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&time)
+        .earliest()
+        .map(|v| v.with_timezone(&Utc))
+}
+
+pub(crate) fn log_if_error<T>(res: Result<T, reqwest::Error>) -> Result<T, ReqError> {
+    match res {
+        Ok(data) => Ok(data),
+        Err(e) => {
+            eprintln!("{:?}", e);
+            Err(ReqError::from(&e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(str: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT)
+            .unwrap()
+            .and_utc()
+    }
+
+    fn time(str: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn tenant_time_from_another_zone() {
+        let start = time("2024-07-01 09:00:00");
+        assert_eq!(
+            instant(start, DEFAULT_TIMEZONE),
+            Some(utc("2024-07-01 00:00:00"))
+        );
+
+        // Travelling in Paris, 1h30 later
+        let now = chrono_tz::Europe::Paris
+            .with_ymd_and_hms(2024, 7, 1, 3, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            tenant_time(now, DEFAULT_TIMEZONE),
+            time("2024-07-01 10:30:00")
+        );
+        let elapsed = now - instant(start, DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(elapsed, chrono::TimeDelta::minutes(90));
+    }
+
+    #[test]
+    fn dates_change_at_the_tenant_midnight() {
+        // Still the 1st in London, already the 2nd in Tokyo.
+        let at = utc("2024-07-01 15:30:00");
+        let date = |tz: Tz| tenant_time(at, tz).format(DATE_FORMAT).to_string();
+        assert_eq!(date(DEFAULT_TIMEZONE), "2024-07-02");
+        assert_eq!(date(chrono_tz::Europe::London), "2024-07-01");
+
+        let tokyo_date = |at: &str| tenant_time(utc(at), DEFAULT_TIMEZONE).date().to_string();
+        assert_eq!(tokyo_date("2024-07-01 14:59:59"), "2024-07-01");
+        assert_eq!(tokyo_date("2024-07-01 15:00:00"), "2024-07-02");
+    }
+
+    #[test]
+    fn instant_of_daylight_saving_changes() {
+        let tz = chrono_tz::America::New_York;
+        // Skipped when the clocks go forward
+        assert_eq!(instant(time("2024-03-10 02:30:00"), tz), None);
+        // The earlier one when the clocks go back
+        assert_eq!(
+            instant(time("2024-11-03 01:30:00"), tz),
+            Some(utc("2024-11-03 05:30:00"))
+        );
+    }
+}
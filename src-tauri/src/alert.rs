@@ -0,0 +1,196 @@
+// Alerts about a stopwatch that may have been forgotten, checked by the ticker while it's running.
+
+use crate::tray;
+use crate::utils::TIME_OF_DAY_FORMAT;
+use chrono::{NaiveDateTime, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, Manager};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimerAlerts {
+    // Alert when the stopwatch has been running longer than this, 0 to disable.
+    pub running_over_minutes: u32,
+    // Alert when the stopwatch is still running at this local time, `%H:%M`. Empty to disable.
+    pub running_after: String,
+}
+impl Default for TimerAlerts {
+    fn default() -> Self {
+        Self {
+            running_over_minutes: 180,
+            running_after: String::from("19:00"),
+        }
+    }
+}
+impl TimerAlerts {
+    pub fn is_valid(&self) -> bool {
+        self.running_after.is_empty() || self.running_after_time().is_some()
+    }
+
+    fn running_after_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(&self.running_after, TIME_OF_DAY_FORMAT).ok()
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimerAlert {
+    RunningOver { minutes: u32 },
+    RunningAfter { time: String },
+}
+impl TimerAlert {
+    pub fn message(&self) -> String {
+        match self {
+            TimerAlert::RunningOver { minutes } => format!(
+                "The stopwatch has been running for over {}h{:02}.",
+                minutes / 60,
+                minutes % 60
+            ),
+            TimerAlert::RunningAfter { time } => {
+                format!("The stopwatch is still running after {}.", time)
+            }
+        }
+    }
+}
+
+// Alerts of a single run of the stopwatch. Each one is only given once.
+#[derive(Debug)]
+pub struct AlertTracker {
+    start: NaiveDateTime,
+    running_over_given: bool,
+    running_after_given: bool,
+}
+impl AlertTracker {
+    pub fn new(start: NaiveDateTime) -> Self {
+        Self {
+            start,
+            running_over_given: false,
+            running_after_given: false,
+        }
+    }
+
+    // When several alerts are due at once, e.g. when the app is opened in the evening, only the first one is given.
+    pub fn check(&mut self, now: NaiveDateTime, alerts: &TimerAlerts) -> Option<TimerAlert> {
+        let mut due = vec![];
+
+        let minutes = alerts.running_over_minutes;
+        if !self.running_over_given
+            && minutes > 0
+            && now - self.start >= TimeDelta::minutes(i64::from(minutes))
+        {
+            self.running_over_given = true;
+            due.push(TimerAlert::RunningOver { minutes });
+        }
+
+        if let Some(time) = alerts.running_after_time() {
+            // Last time it was that time of the day, the stopwatch has to be started before that.
+            let mut at = now.date().and_time(time);
+            if at > now {
+                at -= TimeDelta::days(1);
+            }
+            if !self.running_after_given && self.start < at {
+                self.running_after_given = true;
+                due.push(TimerAlert::RunningAfter {
+                    time: alerts.running_after.clone(),
+                });
+            }
+        }
+
+        due.into_iter().next()
+    }
+}
+
+// Emits a `timer_alert` event with the notification, to let the user stop the stopwatch from the window.
+pub fn notify(app: &AppHandle, alert: &TimerAlert) {
+    show_notification(app, &alert.message());
+    app.emit_all("timer_alert", alert)
+        .expect("failed to emit timer_alert event");
+}
+
+// Shows a native notification, and brings the window to the front as the notification can't open it.
+pub fn show_notification(app: &AppHandle, body: &str) {
+    let identifier = &app.config().tauri.bundle.identifier;
+    if let Err(e) = Notification::new(identifier)
+        .title("CrowdDog")
+        .body(body)
+        .show()
+    {
+        eprintln!("Unable to show the notification: {}", e);
+    }
+    tray::show_window(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crowdlog::utils::TIME_FORMAT;
+
+    fn time(str: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT).unwrap()
+    }
+
+    fn alerts(running_over_minutes: u32, running_after: &str) -> TimerAlerts {
+        TimerAlerts {
+            running_over_minutes,
+            running_after: running_after.to_string(),
+        }
+    }
+
+    #[test]
+    fn alert_once_when_running_too_long() {
+        let alerts = alerts(180, "");
+        let mut tracker = AlertTracker::new(time("2024-07-01 09:00:00"));
+        assert_eq!(tracker.check(time("2024-07-01 11:59:59"), &alerts), None);
+        assert_eq!(
+            tracker.check(time("2024-07-01 12:00:00"), &alerts),
+            Some(TimerAlert::RunningOver { minutes: 180 })
+        );
+        assert_eq!(tracker.check(time("2024-07-01 12:00:01"), &alerts), None);
+    }
+
+    #[test]
+    fn alert_once_when_running_after_time_of_day() {
+        let alerts = alerts(0, "19:00");
+        let mut tracker = AlertTracker::new(time("2024-07-01 09:00:00"));
+        assert_eq!(tracker.check(time("2024-07-01 18:59:59"), &alerts), None);
+        assert_eq!(
+            tracker.check(time("2024-07-01 19:00:00"), &alerts),
+            Some(TimerAlert::RunningAfter {
+                time: String::from("19:00")
+            })
+        );
+        assert_eq!(tracker.check(time("2024-07-01 19:00:01"), &alerts), None);
+
+        // Started after that time on purpose
+        let mut tracker = AlertTracker::new(time("2024-07-01 20:00:00"));
+        assert_eq!(tracker.check(time("2024-07-01 23:00:00"), &alerts), None);
+        assert_eq!(tracker.check(time("2024-07-02 01:00:00"), &alerts), None);
+        assert!(tracker
+            .check(time("2024-07-02 19:00:00"), &alerts)
+            .is_some());
+
+        // Forgotten overnight
+        let mut tracker = AlertTracker::new(time("2024-07-01 09:00:00"));
+        assert!(tracker
+            .check(time("2024-07-02 08:00:00"), &alerts)
+            .is_some());
+    }
+
+    #[test]
+    fn give_one_alert_at_once() {
+        let alerts = alerts(180, "19:00");
+        let mut tracker = AlertTracker::new(time("2024-07-01 09:00:00"));
+        assert_eq!(
+            tracker.check(time("2024-07-01 21:00:00"), &alerts),
+            Some(TimerAlert::RunningOver { minutes: 180 })
+        );
+        assert_eq!(tracker.check(time("2024-07-01 21:00:01"), &alerts), None);
+    }
+
+    #[test]
+    fn validate_time_of_day() {
+        assert!(alerts(0, "").is_valid());
+        assert!(alerts(0, "07:30").is_valid());
+        assert!(!alerts(0, "7pm").is_valid());
+    }
+}
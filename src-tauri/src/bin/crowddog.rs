@@ -0,0 +1,5 @@
+// Command-line interface, see `crowddog --help`.
+
+fn main() {
+    std::process::exit(app_lib::cli::main());
+}
@@ -0,0 +1,364 @@
+// Command-line interface of the stopwatch, run by the `crowddog` binary.
+// It logs in with the credentials saved by the app, so the app has to be logged in once.
+
+use crate::settings::{self, Settings};
+use crate::timer::{self, ToClockStr};
+use chrono::{TimeDelta, Utc};
+use crowdlog::api::{ApiClient, ReqError, StopWatch, StopWatchStatus, MAX_PROCESS_LEVEL};
+use crowdlog::Tz;
+use serde::Serialize;
+
+const USAGE: &str = "Usage: crowddog [--json] <command>
+
+Commands:
+  status                                     Show the stopwatch
+  start <project> <process> [<sub> [<sub>]]  Set the work content and start the stopwatch
+  stop                                       Stop the stopwatch and record the time
+  cancel                                     Reset the stopwatch without recording the time
+  projects                                   List the projects
+  processes <project>                        List the processes of a project
+  history                                    List the recently used work contents
+
+Projects and processes are given by id or by name, or a part of it that matches only one.
+Sub-processes are the processes of level 2 and 3 under the process, when the tenant uses them.
+With --json, the responses of Crowdlog are printed as JSON.";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Status,
+    // Processes from level 1 down.
+    Start {
+        project: String,
+        processes: Vec<String>,
+    },
+    Stop,
+    Cancel,
+    Projects,
+    Processes {
+        project: String,
+    },
+    History,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    command: Command,
+    json: bool,
+}
+
+// Returns the exit code.
+pub fn main() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args = match parse(&args) {
+        Ok(v) => v,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return 2;
+        }
+    };
+    if args.command == Command::Help {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    match tauri::async_runtime::block_on(run(args)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e.message);
+            1
+        }
+    }
+}
+
+fn parse(args: &[String]) -> Result<Args, String> {
+    let json = args.iter().any(|v| v == "--json");
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|v| *v != "--json")
+        .collect();
+
+    let command = match words.as_slice() {
+        [] | ["help"] | ["-h"] | ["--help"] => Command::Help,
+        ["status"] => Command::Status,
+        ["start", project, processes @ ..]
+            if (1..=usize::from(MAX_PROCESS_LEVEL)).contains(&processes.len()) =>
+        {
+            Command::Start {
+                project: project.to_string(),
+                processes: processes.iter().map(|v| v.to_string()).collect(),
+            }
+        }
+        ["stop"] => Command::Stop,
+        ["cancel"] => Command::Cancel,
+        ["projects"] => Command::Projects,
+        ["processes", project] => Command::Processes {
+            project: project.to_string(),
+        },
+        ["history"] => Command::History,
+        [command, ..] => return Err(format!("Invalid arguments of {}", command)),
+    };
+    Ok(Args { command, json })
+}
+
+async fn run(args: Args) -> Result<(), ReqError> {
+    let file_path = settings::default_file_path()
+        .ok_or_else(|| error("Unable to find the config directory"))?;
+    let settings = Settings::load(&file_path);
+    if !settings.credentials.is_valid() {
+        return Err(error("Not logged in. Login from the app first."));
+    }
+    let api = ApiClient::new(&settings.base_url()).with_timezone(settings.timezone());
+    let tz = api.timezone();
+    api.login(&settings.credentials).await?;
+
+    let json = args.json;
+    match args.command {
+        Command::Help => Ok(()),
+        Command::Status => {
+            let sw = api.get_stop_watch().await?;
+            print(json, &sw, || status_line(&sw, tz));
+            Ok(())
+        }
+        Command::Start { project, processes } => {
+            let sw = api.get_stop_watch().await?;
+            if !matches!(sw.status, StopWatchStatus::Clean) {
+                return Err(error(&format!(
+                    "The stopwatch is not available.\n{}",
+                    status_line(&sw, tz)
+                )));
+            }
+
+            let user = api.get_user().await?;
+            let projects = api.get_projects(user.id).await?;
+            let pj = find(&projects, &project, "project", |v| (v.id, &v.name))?;
+            // Each level is looked up under the process found at the level above.
+            let mut process_ids: Vec<u32> = vec![];
+            for (level, process) in (1..).zip(processes.iter()) {
+                let parent_id = process_ids.last().copied();
+                let candidates = api.get_processes(user.id, pj.id, level, parent_id).await?;
+                let pc = find(&candidates, process, "process", |v| (v.id, &v.name))?;
+                process_ids.push(pc.id);
+            }
+
+            api.add_work_content_to_history(pj.id, &process_ids).await?;
+            let memo = sw.work_content.memo();
+            api.set_work_content(sw.id, pj.id, &process_ids, memo)
+                .await?;
+            let sw = api.start_timer(sw.id).await?;
+            print(json, &sw, || status_line(&sw, tz));
+            Ok(())
+        }
+        Command::Stop => {
+            let sw = api.get_stop_watch().await?;
+            let stopped = match sw.status {
+                StopWatchStatus::Started => api.stop_timer(sw.id).await?,
+                StopWatchStatus::NeedToApply => sw,
+                StopWatchStatus::Clean => return Err(error("The stopwatch is not running.")),
+            };
+            let rounding = &settings.preferences.rounding;
+            let end_at = timer::recorded_end_at(&stopped, None, TimeDelta::zero(), rounding, tz);
+            timer::apply(&api, &stopped, end_at.as_deref()).await?;
+            api.reset_timer(stopped.id).await?;
+
+            let end_at = end_at
+                .or_else(|| stopped.stop_at.clone())
+                .unwrap_or_default();
+            print(json, &stopped, || {
+                format!(
+                    "Recorded from {} to {}: {}",
+                    stopped.start_at,
+                    end_at,
+                    stopped.work_content.label()
+                )
+            });
+            Ok(())
+        }
+        Command::Cancel => {
+            let sw = api.get_stop_watch().await?;
+            let sw = api.reset_timer(sw.id).await?;
+            print(json, &sw, || status_line(&sw, tz));
+            Ok(())
+        }
+        Command::Projects => {
+            let user = api.get_user().await?;
+            let projects = api.get_projects(user.id).await?;
+            print(json, &projects, || {
+                lines(projects.iter().map(|v| format!("{}\t{}", v.id, v.name)))
+            });
+            Ok(())
+        }
+        Command::Processes { project } => {
+            let user = api.get_user().await?;
+            let projects = api.get_projects(user.id).await?;
+            let pj = find(&projects, &project, "project", |v| (v.id, &v.name))?;
+            let processes = api.get_processes(user.id, pj.id, 1, None).await?;
+            print(json, &processes, || {
+                lines(processes.iter().map(|v| format!("{}\t{}", v.id, v.name)))
+            });
+            Ok(())
+        }
+        Command::History => {
+            let history = api.get_history().await?;
+            print(json, &history, || {
+                lines(history.iter().filter_map(|wc| {
+                    let (project_id, process_id) = wc.ids()?;
+                    Some(format!("{}\t{}\t{}", project_id, process_id, wc.label()))
+                }))
+            });
+            Ok(())
+        }
+    }
+}
+
+fn status_line(sw: &StopWatch, tz: Tz) -> String {
+    let label = sw.work_content.label();
+    match sw.status {
+        StopWatchStatus::Started => match timer::elapsed(sw, Utc::now(), tz) {
+            Some(elapsed) => format!(
+                "Running for {} since {}: {}",
+                elapsed.to_clock_str(),
+                sw.start_at,
+                label
+            ),
+            None => format!("Running since {}: {}", sw.start_at, label),
+        },
+        StopWatchStatus::NeedToApply => format!(
+            "Stopped at {} without recording: {}",
+            sw.stop_at.as_deref().unwrap_or("-"),
+            label
+        ),
+        StopWatchStatus::Clean => format!("Not running: {}", label),
+    }
+}
+
+fn print<T: Serialize>(json: bool, data: &T, text: impl FnOnce() -> String) {
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(data).expect("Unable to serialize JSON")
+        );
+    } else {
+        println!("{}", text());
+    }
+}
+
+fn lines(lines: impl Iterator<Item = String>) -> String {
+    lines.collect::<Vec<_>>().join("\n")
+}
+
+// By id, by name regardless of case, or by the only name containing the query.
+fn find<'a, T>(
+    items: &'a [T],
+    query: &str,
+    kind: &str,
+    id_and_name: impl Fn(&T) -> (u32, &str),
+) -> Result<&'a T, ReqError> {
+    if let Ok(id) = query.parse::<u32>() {
+        if let Some(item) = items.iter().find(|v| id_and_name(v).0 == id) {
+            return Ok(item);
+        }
+    }
+
+    let lowercase = query.to_lowercase();
+    if let Some(item) = items
+        .iter()
+        .find(|v| id_and_name(v).1.to_lowercase() == lowercase)
+    {
+        return Ok(item);
+    }
+    let matches: Vec<&T> = items
+        .iter()
+        .filter(|v| id_and_name(v).1.to_lowercase().contains(&lowercase))
+        .collect();
+    match matches.as_slice() {
+        [item] => Ok(item),
+        [] => Err(error(&format!("No {} matches {:?}", kind, query))),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|v| id_and_name(v).1).collect();
+            Err(error(&format!(
+                "Several {} names match {:?}: {}",
+                kind,
+                query,
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+fn error(message: &str) -> ReqError {
+    ReqError {
+        status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+        message: message.to_string(),
+        source: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crowdlog::api::Project;
+
+    fn args(line: &str) -> Result<Args, String> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            args("start acme design"),
+            Ok(Args {
+                command: Command::Start {
+                    project: String::from("acme"),
+                    processes: vec![String::from("design")],
+                },
+                json: false,
+            })
+        );
+        assert_eq!(
+            args("start acme development backend api").map(|v| v.command),
+            Ok(Command::Start {
+                project: String::from("acme"),
+                processes: ["development", "backend", "api"]
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect(),
+            })
+        );
+        assert_eq!(
+            args("--json processes 12"),
+            Ok(Args {
+                command: Command::Processes {
+                    project: String::from("12"),
+                },
+                json: true,
+            })
+        );
+        assert_eq!(args("").map(|v| v.command), Ok(Command::Help));
+        assert!(args("start acme").is_err());
+        assert!(args("start acme development backend api v2").is_err());
+        assert!(args("status now").is_err());
+        assert!(args("restart").is_err());
+    }
+
+    #[test]
+    fn find_by_id_or_name() {
+        let projects: Vec<Project> = [(1, "Acme Corp"), (2, "Acme Labs"), (3, "Globex")]
+            .iter()
+            .map(|(id, name)| Project {
+                id: *id,
+                name: name.to_string(),
+            })
+            .collect();
+        let find =
+            |query: &str| find(&projects, query, "project", |v| (v.id, &v.name)).map(|v| v.id);
+
+        assert_eq!(find("2").ok(), Some(2));
+        assert_eq!(find("globex").ok(), Some(3));
+        assert_eq!(find("labs").ok(), Some(2));
+        assert!(find("acme").is_err());
+        assert!(find("initech").is_err());
+    }
+}
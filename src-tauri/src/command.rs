@@ -1,54 +1,134 @@
-use crate::api::{
-    ApiClient, Process, Project, ReqError, StopWatch, StopWatchStatus, User, WorkContent,
+use crate::offline::{self, OfflineStore};
+use crate::{idle, rounding, search, settings, shortcut, summary, timer, tray};
+use crowdlog::api::{
+    ApiClient, Process, Project, ReqError, StopWatch, Timesheet, TimesheetInput, User, WorkContent,
 };
-use crate::settings;
-use crate::utils::{cancellation_token, Canceller};
-use chrono::{NaiveDateTime, TimeDelta, Utc};
-use std::{sync::Mutex, thread};
+use crowdlog::settings::Credentials;
+use crowdlog::utils;
+use std::sync::Mutex;
 use tauri::{Manager, State};
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn login(
-    credentials: settings::Credentials,
+    credentials: Credentials,
     app_handle: tauri::AppHandle,
     api: State<'_, ApiClient>,
-    managed_cred: State<'_, Mutex<settings::Credentials>>,
+    managed_settings: State<'_, Mutex<settings::Settings>>,
 ) -> Result<(), ReqError> {
     // Try to login with the new cred
     api.login(&credentials).await?;
 
     {
-        let mut cred = managed_cred.lock().unwrap();
+        let mut settings = managed_settings.lock().unwrap();
 
         // Save the new cred
-        cred.replace(&credentials);
-        cred.save(&app_handle);
+        settings.credentials.replace(&credentials);
+        settings.save(&settings::get_file_path(&app_handle));
     }
 
     Ok(())
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn re_login(
+pub async fn clear_data(
+    app_handle: tauri::AppHandle,
     api: State<'_, ApiClient>,
-    managed_cred: State<'_, Mutex<settings::Credentials>>,
+    managed_settings: State<'_, Mutex<settings::Settings>>,
 ) -> Result<(), ReqError> {
-    let cred = managed_cred.lock().unwrap().clone();
-    api.login(&cred).await?;
+    let mut settings = managed_settings.lock().unwrap();
+    settings.clear_credentials(&settings::get_file_path(&app_handle));
+    api.clear_credentials();
+    app_handle
+        .state::<timer::StopWatchState>()
+        .send_replace(None);
     Ok(())
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn clear_data(
+pub async fn get_preferences(
+    managed_settings: State<'_, Mutex<settings::Settings>>,
+) -> Result<settings::Preferences, ReqError> {
+    Ok(managed_settings.lock().unwrap().preferences.clone())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_preferences(
+    preferences: settings::Preferences,
     app_handle: tauri::AppHandle,
-    managed_cred: State<'_, Mutex<settings::Credentials>>,
+    managed_settings: State<'_, Mutex<settings::Settings>>,
 ) -> Result<(), ReqError> {
-    let mut cred = managed_cred.lock().unwrap().clone();
-    cred.clear(&app_handle);
+    if !preferences.rounding.is_valid() {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: format!(
+                "Rounding unit must be one of {:?} minutes",
+                rounding::UNIT_MINUTES
+            ),
+            source: None,
+        });
+    }
+
+    if !preferences.idle.is_valid() {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: String::from("Idle threshold must be at least 1 minute"),
+            source: None,
+        });
+    }
+    if !preferences.alerts.is_valid() {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: format!(
+                "Invalid time of the day: {}",
+                preferences.alerts.running_after
+            ),
+            source: None,
+        });
+    }
+    if !preferences.working_hours.is_valid() {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: String::from(
+                "Working hours must start before they end, with a reminder after at least 1 minute",
+            ),
+            source: None,
+        });
+    }
+    if let Some(accelerator) = preferences.shortcuts.duplicate() {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: format!("Shortcut {} is assigned more than once", accelerator),
+            source: None,
+        });
+    }
+
+    let current = managed_settings
+        .lock()
+        .unwrap()
+        .preferences
+        .shortcuts
+        .clone();
+    if preferences.shortcuts != current {
+        if let Err(e) = shortcut::register(&app_handle, &preferences.shortcuts) {
+            // Keep the ones that were working
+            if let Err(e) = shortcut::register(&app_handle, &current) {
+                eprintln!("Unable to restore the shortcuts: {}", e);
+            }
+            return Err(ReqError {
+                status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+                message: String::from("Invalid shortcut, or already used by another app"),
+                source: Some(e.to_string()),
+            });
+        }
+    }
+
+    let mut settings = managed_settings.lock().unwrap();
+    settings.preferences = preferences;
+    settings.save(&settings::get_file_path(&app_handle));
     Ok(())
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct InitialData {
     user: User,
     stop_watch: StopWatch,
@@ -57,7 +137,35 @@ pub struct InitialData {
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn init_data(api: State<'_, ApiClient>) -> Result<InitialData, ReqError> {
+pub async fn init_data(
+    app_handle: tauri::AppHandle,
+    api: State<'_, ApiClient>,
+    store: State<'_, OfflineStore>,
+) -> Result<InitialData, ReqError> {
+    let data = match fetch_initial_data(&api).await {
+        Ok(data) => {
+            store.put_cache(offline::INITIAL_DATA, &data);
+            store.put_cache(offline::PROJECTS, &data.projects);
+            data
+        }
+        // Show the last known data while offline, with the stopwatch as changed since then
+        Err(e) if e.is_unreachable() => {
+            let mut data: InitialData = store.get_cache(offline::INITIAL_DATA).ok_or(e)?;
+            let known = app_handle.state::<timer::StopWatchState>().borrow().clone();
+            data.stop_watch = known.unwrap_or(data.stop_watch);
+            data
+        }
+        Err(e) => return Err(e),
+    };
+
+    timer::update_state(&app_handle, &data.stop_watch);
+    tray::refresh_menu(&app_handle);
+    search::refresh(&app_handle);
+
+    Ok(data)
+}
+
+async fn fetch_initial_data(api: &ApiClient) -> Result<InitialData, ReqError> {
     let user = api.get_user().await?;
     let stop_watch = api.get_stop_watch().await?;
     let history = api.get_history().await?;
@@ -71,120 +179,271 @@ pub async fn init_data(api: State<'_, ApiClient>) -> Result<InitialData, ReqErro
     })
 }
 
+// The stopwatch as the app knows it, kept up to date by `stop_watch_changed` events afterwards.
 #[tauri::command(rename_all = "snake_case")]
-pub async fn get_my_patterns(api: State<'_, ApiClient>) -> Result<Vec<WorkContent>, ReqError> {
-    api.get_my_patterns().await
+pub async fn get_state(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    timer::current(&app_handle).await
 }
 
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_my_patterns(
+    api: State<'_, ApiClient>,
+    store: State<'_, OfflineStore>,
+) -> Result<Vec<WorkContent>, ReqError> {
+    let my_patterns = api.get_my_patterns().await?;
+    // Also shown in the tray menu.
+    store.put_cache(offline::MY_PATTERNS, &my_patterns);
+    Ok(my_patterns)
+}
+
+// Level 1 processes by default, or the ones under `parent_id` at a deeper `level`.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_processes(
     user_id: u32,
     project_id: u32,
+    level: Option<u8>,
+    parent_id: Option<u32>,
     api: State<'_, ApiClient>,
+    store: State<'_, OfflineStore>,
 ) -> Result<Vec<Process>, ReqError> {
-    api.get_processes(user_id, project_id).await
+    let level = level.unwrap_or(1);
+    let key = offline::processes(project_id, level, parent_id);
+    match api
+        .get_processes(user_id, project_id, level, parent_id)
+        .await
+    {
+        Ok(processes) => {
+            store.put_cache(&key, &processes);
+            Ok(processes)
+        }
+        Err(e) if e.is_unreachable() => store.get_cache(&key).ok_or(e),
+        Err(e) => Err(e),
+    }
+}
+
+// Pairs of a project and a process matching every word of the query, best matches first.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn search_work_contents(
+    app_handle: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+    index: State<'_, search::SearchHandle>,
+) -> Result<Vec<search::SearchResult>, ReqError> {
+    // Built on demand when the background build has not finished yet, or has failed.
+    if !index.lock().unwrap().is_built() {
+        let built = search::build(&app_handle).await?;
+        *index.lock().unwrap() = built;
+    }
+
+    let limit = limit.unwrap_or(search::DEFAULT_LIMIT);
+    Ok(index.lock().unwrap().search(&query, limit))
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ProcessesPage {
+    project_id: u32,
+    level: u8,
+    parent_id: Option<u32>,
+    processes: Vec<Process>,
+    is_last: bool,
+}
+
+// Same as `get_processes`, but every page is emitted as a `processes_page` event as soon as it's received.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn stream_processes(
+    user_id: u32,
+    project_id: u32,
+    level: Option<u8>,
+    parent_id: Option<u32>,
+    app_handle: tauri::AppHandle,
+    api: State<'_, ApiClient>,
+    store: State<'_, OfflineStore>,
+) -> Result<(), ReqError> {
+    let level = level.unwrap_or(1);
+    let key = offline::processes(project_id, level, parent_id);
+    let emit = |processes: Vec<Process>, is_last: bool| {
+        let page = ProcessesPage {
+            project_id,
+            level,
+            parent_id,
+            processes,
+            is_last,
+        };
+        app_handle
+            .emit_all("processes_page", page)
+            .expect("failed to emit processes_page event");
+    };
+
+    let mut all = vec![];
+    let res = api
+        .stream_processes(user_id, project_id, level, parent_id, |page, is_last| {
+            all.extend(page.iter().cloned());
+            emit(page, is_last);
+        })
+        .await;
+    match res {
+        Ok(()) => {
+            store.put_cache(&key, &all);
+            Ok(())
+        }
+        Err(e) if e.is_unreachable() && all.is_empty() => {
+            emit(store.get_cache(&key).ok_or(e)?, true);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
 }
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn set_work_content(
-    stop_watch: StopWatch,
     project_id: u32,
     process_id: u32,
-    api: State<'_, ApiClient>,
+    sub_process_ids: Option<Vec<u32>>,
+    app_handle: tauri::AppHandle,
 ) -> Result<WorkContent, ReqError> {
-    api.add_work_content_to_history(project_id, process_id)
-        .await?;
-    api.set_work_content(stop_watch.id, project_id, process_id)
-        .await
+    let stop_watch = timer::current(&app_handle).await?;
+    let sub_process_ids = sub_process_ids.unwrap_or_default();
+    timer::set_work_content(
+        &app_handle,
+        &stop_watch,
+        project_id,
+        process_id,
+        &sub_process_ids,
+    )
+    .await
 }
 
-pub type TimerHandle = Mutex<Option<Canceller>>;
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_memo(memo: String, app_handle: tauri::AppHandle) -> Result<WorkContent, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::set_memo(&app_handle, &stop_watch, &memo).await
+}
 
-trait ToClockStr {
-    fn to_clock_str(&self) -> String;
+#[tauri::command(rename_all = "snake_case")]
+pub async fn preview_stop(
+    app_handle: tauri::AppHandle,
+) -> Result<Option<timer::StopPreview>, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    Ok(timer::preview_stop(&app_handle, &stop_watch))
 }
-// struct Delta(TimeDelta);
-impl ToClockStr for TimeDelta {
-    fn to_clock_str(&self) -> String {
-        let h = self.num_hours();
-        let m = self.num_minutes() % 60;
-        let s = self.num_seconds() % 60;
-        format!("{:02}:{:02}:{:02}", h, m, s)
-    }
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn start_timer(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::start(&app_handle, stop_watch).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn start_timer(
-    stop_watch: StopWatch,
+pub async fn stop_timer(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::stop(&app_handle, stop_watch).await
+}
+
+// Records the running entry until now and starts the given work content, or rolls back to a stop on failure.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn switch_task(
+    project_id: u32,
+    process_id: u32,
+    sub_process_ids: Option<Vec<u32>>,
     app_handle: tauri::AppHandle,
-    api: State<'_, ApiClient>,
-    timer_handle: State<'_, TimerHandle>,
 ) -> Result<StopWatch, ReqError> {
-    let sw = match stop_watch.status {
-        StopWatchStatus::Started => stop_watch,
-        StopWatchStatus::Clean => api.start_timer(stop_watch.id).await?,
-        StopWatchStatus::NeedToApply => return Err(ReqError {
-            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
-            message: String::from("Timer is stopped without applying a work content. Fix it in the stop watch page in the CrowdLog's website."),
-            source: None,
-        }),
-    };
+    let stop_watch = timer::current(&app_handle).await?;
+    let sub_process_ids = sub_process_ids.unwrap_or_default();
+    timer::switch(
+        &app_handle,
+        stop_watch,
+        project_id,
+        process_id,
+        &sub_process_ids,
+    )
+    .await
+}
 
-    let mut t_handle = timer_handle.lock().unwrap();
-    if t_handle.is_some() {
-        return Ok(sw);
-    }
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cancel_timer(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::cancel(&app_handle, stop_watch).await
+}
 
-    let start_at = sw.start_at.clone();
-    let (canceller, token) = cancellation_token();
-    t_handle.get_or_insert(canceller);
-    thread::spawn(move || {
-        let start = NaiveDateTime::parse_from_str(&start_at, "%Y-%m-%d %H:%M:%S").unwrap();
-        loop {
-            let elapsed = Utc::now().signed_duration_since(start.and_utc());
-            let fmtd = elapsed.to_clock_str();
-            app_handle
-                .emit_all("timer_tick", Some(fmtd))
-                .expect("failed to emit timer_tick event");
-
-            if token.should_cancel() {
-                break;
-            }
-            thread::sleep(std::time::Duration::from_secs(1));
-        }
-    });
+// Applies the choice of the user about the time they have been idle, see `idle_detected` events.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn resolve_idle(
+    period: idle::IdlePeriod,
+    choice: idle::IdleChoice,
+    app_handle: tauri::AppHandle,
+) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    idle::resolve(&app_handle, stop_watch, &period, choice).await
+}
 
-    Ok(sw)
+#[tauri::command(rename_all = "snake_case")]
+pub async fn apply_pending(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::apply_pending(&app_handle, stop_watch).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn stop_timer(
-    stop_watch: StopWatch,
+pub async fn discard_pending(app_handle: tauri::AppHandle) -> Result<StopWatch, ReqError> {
+    let stop_watch = timer::current(&app_handle).await?;
+    timer::cancel(&app_handle, stop_watch).await
+}
+
+// Dates are `%Y-%m-%d`, both included, today of the tenant by default.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_timesheets(
+    start_date: Option<String>,
+    end_date: Option<String>,
     api: State<'_, ApiClient>,
-    timer_handle: State<'_, TimerHandle>,
-) -> Result<StopWatch, ReqError> {
-    api.stop_timer(stop_watch.id).await?;
-    api.apply_timer(stop_watch.id).await?;
-    let sw = api.reset_timer(stop_watch.id).await?;
+) -> Result<Vec<Timesheet>, ReqError> {
+    let today = utils::today(api.timezone());
+    let start_date = start_date.unwrap_or_else(|| today.clone());
+    let end_date = end_date.unwrap_or(today);
+    api.get_timesheets(&start_date, &end_date).await
+}
 
-    let mut t_handle = timer_handle.lock().unwrap();
-    if let Some(handle) = t_handle.take() {
-        handle.cancel();
-    }
-    Ok(sw)
+#[tauri::command(rename_all = "snake_case")]
+pub async fn create_timesheet(
+    input: TimesheetInput,
+    api: State<'_, ApiClient>,
+) -> Result<Timesheet, ReqError> {
+    api.create_timesheet(&input).await
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn cancel_timer(
-    stop_watch: StopWatch,
+pub async fn edit_timesheet(
+    id: u32,
+    input: TimesheetInput,
     api: State<'_, ApiClient>,
-    timer_handle: State<'_, TimerHandle>,
-) -> Result<StopWatch, ReqError> {
-    let sw = api.reset_timer(stop_watch.id).await?;
+) -> Result<Timesheet, ReqError> {
+    api.edit_timesheet(id, &input).await
+}
 
-    let mut t_handle = timer_handle.lock().unwrap();
-    if let Some(handle) = t_handle.take() {
-        handle.cancel();
-    }
-    Ok(sw)
+#[tauri::command(rename_all = "snake_case")]
+pub async fn delete_timesheet(id: u32, api: State<'_, ApiClient>) -> Result<(), ReqError> {
+    api.delete_timesheet(id).await
+}
+
+// `date` is any day of the period, today by default. Format: `%Y-%m-%d`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_summary(
+    period: summary::Period,
+    date: Option<String>,
+    api: State<'_, ApiClient>,
+) -> Result<summary::Summary, ReqError> {
+    let date = date.unwrap_or_else(|| utils::today(api.timezone()));
+    let date =
+        chrono::NaiveDate::parse_from_str(&date, utils::DATE_FORMAT).map_err(|e| ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: format!("Invalid date: {}", date),
+            source: Some(e.to_string()),
+        })?;
+
+    let (start, end) = period.range(date);
+    let timesheets = api
+        .get_timesheets(
+            &start.format(utils::DATE_FORMAT).to_string(),
+            &end.format(utils::DATE_FORMAT).to_string(),
+        )
+        .await?;
+    Ok(summary::summarize(start, end, &timesheets))
 }
@@ -0,0 +1,360 @@
+// Notices when the user is away while the stopwatch is running, so that the idle time can be left out on return.
+
+use crate::settings::Settings;
+use crate::ticker;
+use crate::timer;
+use crate::tray;
+use chrono::{NaiveDateTime, TimeDelta};
+use crowdlog::api::{ReqError, StopWatch};
+use crowdlog::utils::TIME_FORMAT;
+use serde::{Deserialize, Serialize};
+use std::{sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+const INTERVAL: Duration = Duration::from_secs(5);
+
+pub type IdleHandle = Mutex<IdleTracker>;
+
+// Time since the last input of the user, None when it can't be known.
+pub trait IdleSource: Send + 'static {
+    fn idle_time(&self) -> Option<Duration>;
+}
+
+// Idle time of the desktop session. Only available on Linux, with `xprintidle` on X11 or GNOME on Wayland.
+pub struct SystemIdle;
+impl IdleSource for SystemIdle {
+    #[cfg(target_os = "linux")]
+    fn idle_time(&self) -> Option<Duration> {
+        let millis = output_of("xprintidle", &[]).and_then(|v| v.trim().parse().ok());
+        let millis = millis.or_else(|| {
+            let args = [
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ];
+            output_of("gdbus", &args).and_then(|v| parse_gdbus_uint64(&v))
+        })?;
+        Some(Duration::from_millis(millis))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn output_of(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+// gdbus prints values like `(uint64 12345,)`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_gdbus_uint64(output: &str) -> Option<u64> {
+    output
+        .trim()
+        .strip_prefix("(uint64 ")?
+        .split(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct IdleDetection {
+    pub enabled: bool,
+    pub threshold_minutes: u32,
+}
+impl Default for IdleDetection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_minutes: 10,
+        }
+    }
+}
+impl IdleDetection {
+    pub fn is_valid(&self) -> bool {
+        self.threshold_minutes > 0
+    }
+
+    fn threshold(&self) -> TimeDelta {
+        TimeDelta::minutes(i64::from(self.threshold_minutes))
+    }
+}
+
+// From the last input before going idle to the first one after. Format: `%Y-%m-%d %H:%M:%S`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IdlePeriod {
+    pub start_at: String,
+    pub end_at: String,
+}
+impl IdlePeriod {
+    fn duration(&self) -> Option<TimeDelta> {
+        let start = NaiveDateTime::parse_from_str(&self.start_at, TIME_FORMAT).ok()?;
+        let end = NaiveDateTime::parse_from_str(&self.end_at, TIME_FORMAT).ok()?;
+        Some(end.signed_duration_since(start))
+    }
+}
+
+// What to do with an idle period, chosen by the user on return.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleChoice {
+    // Record it as work.
+    Keep,
+    // Keep the stopwatch running, but leave it out of the recorded time when it's stopped.
+    Subtract,
+    // Stop the stopwatch as if it had been stopped when the user went idle.
+    StopAtIdleStart,
+}
+
+#[derive(Debug, Default)]
+pub struct IdleTracker {
+    // Start of the running stopwatch.
+    running_since: Option<NaiveDateTime>,
+    idle_since: Option<NaiveDateTime>,
+    // Idle time to leave out of the recorded time of the running stopwatch.
+    subtracted: TimeDelta,
+}
+impl IdleTracker {
+    // The subtracted time belongs to the stopwatch, so it's dropped when another one is started.
+    pub fn set_running_since(&mut self, start: NaiveDateTime) {
+        if self.running_since != Some(start) {
+            self.running_since = Some(start);
+            self.idle_since = None;
+            self.subtracted = TimeDelta::zero();
+        }
+    }
+
+    // Returns the idle period once the user is back from it.
+    pub fn poll(
+        &mut self,
+        source: &dyn IdleSource,
+        now: NaiveDateTime,
+        is_running: bool,
+        threshold: TimeDelta,
+    ) -> Option<IdlePeriod> {
+        let start = match self.running_since {
+            Some(v) if is_running => v,
+            _ => {
+                self.idle_since = None;
+                return None;
+            }
+        };
+        let idle = TimeDelta::from_std(source.idle_time()?).ok()?;
+        let last_input = now - idle;
+
+        if idle >= threshold {
+            // Time before the stopwatch was started is not recorded anyway.
+            self.idle_since.get_or_insert(last_input.max(start));
+            return None;
+        }
+        let idle_since = self.idle_since.take()?;
+        if last_input <= idle_since {
+            return None;
+        }
+        Some(IdlePeriod {
+            start_at: idle_since.format(TIME_FORMAT).to_string(),
+            end_at: last_input.format(TIME_FORMAT).to_string(),
+        })
+    }
+
+    pub fn subtract(&mut self, period: &IdlePeriod) {
+        if let Some(duration) = period.duration() {
+            self.subtracted += duration.max(TimeDelta::zero());
+        }
+    }
+
+    pub fn subtracted(&self) -> TimeDelta {
+        self.subtracted
+    }
+
+    pub fn take_subtracted(&mut self) -> TimeDelta {
+        std::mem::take(&mut self.subtracted)
+    }
+}
+
+// Emits an `idle_detected` event with the idle period when the user is back, and brings the window to the front.
+pub fn spawn(app: &AppHandle, source: impl IdleSource) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(INTERVAL);
+        let detection = app
+            .state::<Mutex<Settings>>()
+            .lock()
+            .unwrap()
+            .preferences
+            .idle;
+        if !detection.enabled {
+            continue;
+        }
+
+        let is_running = ticker::is_running(&app);
+        let now = timer::tenant_now(&app);
+        let period = app.state::<IdleHandle>().lock().unwrap().poll(
+            &source,
+            now,
+            is_running,
+            detection.threshold(),
+        );
+        if let Some(period) = period {
+            tray::show_window(&app);
+            app.emit_all("idle_detected", &period)
+                .expect("failed to emit idle_detected event");
+        }
+    });
+}
+
+pub async fn resolve(
+    app: &AppHandle,
+    stop_watch: StopWatch,
+    period: &IdlePeriod,
+    choice: IdleChoice,
+) -> Result<StopWatch, ReqError> {
+    match choice {
+        IdleChoice::Keep => Ok(stop_watch),
+        IdleChoice::Subtract => {
+            app.state::<IdleHandle>().lock().unwrap().subtract(period);
+            Ok(stop_watch)
+        }
+        IdleChoice::StopAtIdleStart => {
+            let at = NaiveDateTime::parse_from_str(&period.start_at, TIME_FORMAT).map_err(|e| {
+                ReqError {
+                    status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+                    message: format!("Invalid idle start: {}", period.start_at),
+                    source: Some(e.to_string()),
+                }
+            })?;
+            timer::stop_at(app, stop_watch, Some(at)).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // Idle time set by the test.
+    #[derive(Clone, Default)]
+    struct FakeIdle(Arc<Mutex<Option<Duration>>>);
+    impl FakeIdle {
+        fn set(&self, minutes: u64) {
+            *self.0.lock().unwrap() = Some(Duration::from_secs(minutes * 60));
+        }
+    }
+    impl IdleSource for FakeIdle {
+        fn idle_time(&self) -> Option<Duration> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn time(str: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT).unwrap()
+    }
+
+    fn period(start_at: &str, end_at: &str) -> IdlePeriod {
+        IdlePeriod {
+            start_at: start_at.to_string(),
+            end_at: end_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn detect_idle_period_on_return() {
+        let threshold = TimeDelta::minutes(10);
+        let source = FakeIdle::default();
+        let mut tracker = IdleTracker::default();
+        tracker.set_running_since(time("2024-07-01 09:00:00"));
+
+        source.set(3);
+        let now = time("2024-07-01 10:00:00");
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+
+        // Away since 10:05
+        source.set(12);
+        let now = time("2024-07-01 10:17:00");
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+        source.set(40);
+        let now = time("2024-07-01 10:45:00");
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+
+        // Back at 10:50
+        source.set(1);
+        let now = time("2024-07-01 10:51:00");
+        assert_eq!(
+            tracker.poll(&source, now, true, threshold),
+            Some(period("2024-07-01 10:05:00", "2024-07-01 10:50:00"))
+        );
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+    }
+
+    #[test]
+    fn ignore_idle_time_while_not_running() {
+        let threshold = TimeDelta::minutes(10);
+        let source = FakeIdle::default();
+        let mut tracker = IdleTracker::default();
+
+        source.set(30);
+        let now = time("2024-07-01 10:30:00");
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+        source.set(0);
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+
+        // Started while idle, so only the time after the start is reported
+        tracker.set_running_since(time("2024-07-01 10:00:00"));
+        source.set(40);
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+        source.set(0);
+        let now = time("2024-07-01 10:31:00");
+        assert_eq!(
+            tracker.poll(&source, now, true, threshold),
+            Some(period("2024-07-01 10:00:00", "2024-07-01 10:31:00"))
+        );
+
+        // Stopped while idle
+        source.set(30);
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+        assert_eq!(tracker.poll(&source, now, false, threshold), None);
+        source.set(0);
+        assert_eq!(tracker.poll(&source, now, true, threshold), None);
+    }
+
+    #[test]
+    fn subtracted_time_belongs_to_the_stopwatch() {
+        let mut tracker = IdleTracker::default();
+        tracker.set_running_since(time("2024-07-01 09:00:00"));
+        tracker.subtract(&period("2024-07-01 10:05:00", "2024-07-01 10:50:00"));
+        tracker.subtract(&period("2024-07-01 12:00:00", "2024-07-01 12:15:00"));
+        assert_eq!(tracker.subtracted(), TimeDelta::minutes(60));
+
+        // Same stopwatch, e.g. after a sync
+        tracker.set_running_since(time("2024-07-01 09:00:00"));
+        assert_eq!(tracker.take_subtracted(), TimeDelta::minutes(60));
+        assert_eq!(tracker.subtracted(), TimeDelta::zero());
+
+        tracker.subtract(&period("2024-07-01 12:00:00", "2024-07-01 12:15:00"));
+        tracker.set_running_since(time("2024-07-01 13:00:00"));
+        assert_eq!(tracker.subtracted(), TimeDelta::zero());
+    }
+
+    #[test]
+    fn parse_gdbus_output() {
+        assert_eq!(parse_gdbus_uint64("(uint64 12345,)\n"), Some(12345));
+        assert_eq!(parse_gdbus_uint64("Error: no such object"), None);
+    }
+}
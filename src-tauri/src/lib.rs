@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+
+use crowdlog::ApiClient;
+use tauri::{Manager, WindowEvent};
+use tokio::sync::watch;
+
+mod alert;
+pub mod cli;
+mod command;
+mod idle;
+mod offline;
+mod poll;
+mod reminder;
+mod rounding;
+mod search;
+mod settings;
+mod shortcut;
+mod summary;
+mod sync;
+mod ticker;
+mod timer;
+mod tray;
+mod utils;
+
+pub fn run() {
+    tauri::Builder::default()
+        .setup(|app| {
+            let handle = app.handle();
+            let settings = settings::Settings::init(&settings::get_file_path(&handle));
+
+            let client = ApiClient::new(&settings.base_url()).with_timezone(settings.timezone());
+            app.manage(client);
+
+            let store_path = app
+                .path_resolver()
+                .app_data_dir()
+                .expect("Unable to get app data dir")
+                .join("offline.sqlite3");
+            let store = offline::OfflineStore::open(&store_path).unwrap_or_else(|e| {
+                eprintln!("Unable to open the offline store, using memory: {}", e);
+                offline::OfflineStore::open_in_memory().expect("Unable to open the offline store")
+            });
+            app.manage(store);
+
+            let mut is_logged_in = false;
+            if settings.credentials.is_valid() {
+                // Try to login with the saved cred
+                let api = app.state::<ApiClient>();
+                match tauri::async_runtime::block_on(api.login(&settings.credentials)) {
+                    Ok(()) => is_logged_in = true,
+                    // Work offline, it will login again once Crowdlog is reachable
+                    Err(e) if e.is_unreachable() => {
+                        api.set_credentials(&settings.credentials);
+                        is_logged_in = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+            let main_window = app.get_window("main").unwrap();
+            let mut url = main_window.url();
+            url.set_path("/login");
+            if is_logged_in {
+                url.set_query(Some("logged_in=true"));
+            }
+            main_window
+                .eval(&format!("window.location.replace('{}')", url.as_str()))
+                .expect("Unable to set window location");
+
+            if let Err(e) = shortcut::register(&handle, &settings.preferences.shortcuts) {
+                eprintln!("Unable to register the shortcuts: {}", e);
+            }
+            app.manage(Mutex::new(settings));
+            app.manage::<timer::StopWatchState>(watch::Sender::new(None));
+            app.manage::<ticker::Elapsed>(watch::Sender::new(None));
+            app.manage::<search::SearchHandle>(Mutex::new(search::SearchIndex::default()));
+            app.manage::<idle::IdleHandle>(Mutex::new(idle::IdleTracker::default()));
+            ticker::spawn(&handle);
+            sync::spawn(&handle);
+            idle::spawn(&handle, idle::SystemIdle);
+            reminder::spawn(&handle);
+            poll::spawn(&handle);
+
+            Ok(())
+        })
+        .system_tray(tray::build())
+        .on_system_tray_event(tray::on_event)
+        .on_window_event(|event| {
+            // Keep running in the tray when the window is closed. Quit from the tray menu.
+            if let WindowEvent::CloseRequested { api, .. } = event.event() {
+                let _ = event.window().hide();
+                api.prevent_close();
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            command::login,
+            command::init_data,
+            command::get_state,
+            command::clear_data,
+            command::get_my_patterns,
+            command::get_processes,
+            command::stream_processes,
+            command::search_work_contents,
+            command::set_work_content,
+            command::set_memo,
+            command::get_preferences,
+            command::set_preferences,
+            command::preview_stop,
+            command::start_timer,
+            command::stop_timer,
+            command::switch_task,
+            command::cancel_timer,
+            command::resolve_idle,
+            command::apply_pending,
+            command::discard_pending,
+            command::get_timesheets,
+            command::create_timesheet,
+            command::edit_timesheet,
+            command::delete_timesheet,
+            command::get_summary,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
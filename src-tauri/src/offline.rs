@@ -0,0 +1,216 @@
+// Local persistent store used while Crowdlog is unreachable.
+// It queues the stopwatch actions to replay them later (see `sync.rs`), and caches the last responses to show them offline.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Cache keys.
+pub const INITIAL_DATA: &str = "initial_data";
+pub const PROJECTS: &str = "projects";
+pub const MY_PATTERNS: &str = "my_patterns";
+pub fn processes(project_id: u32, level: u8, parent_id: Option<u32>) -> String {
+    format!(
+        "processes:{}:{}:{}",
+        project_id,
+        level,
+        parent_id.unwrap_or(0)
+    )
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Start,
+    Stop,
+    Cancel,
+    SetWorkContent {
+        project_id: u32,
+        process_id: u32,
+        // Processes of level 2 and 3. Missing in actions queued by older versions.
+        #[serde(default)]
+        sub_process_ids: Vec<u32>,
+    },
+    SetMemo {
+        memo: String,
+    },
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct QueuedAction {
+    pub id: i64,
+    #[serde(flatten)]
+    pub action: Action,
+    // Time of the tenant when the action has been done. Format: `%Y-%m-%d %H:%M:%S`.
+    pub recorded_at: String,
+}
+
+pub struct OfflineStore {
+    conn: Mutex<Connection>,
+}
+impl OfflineStore {
+    pub fn open(path: &Path) -> Result<Self, rusqlite::Error> {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    // Used when the file can't be opened, so actions are only kept until the app quits.
+    pub fn open_in_memory() -> Result<Self, rusqlite::Error> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pending_actions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                payload TEXT NOT NULL,
+                recorded_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS cache (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn queue(&self, action: &Action, recorded_at: &str) -> Result<(), rusqlite::Error> {
+        let payload = serde_json::to_string(action).expect("Unable to serialize JSON");
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO pending_actions (payload, recorded_at) VALUES (?1, ?2)",
+            params![payload, recorded_at],
+        )?;
+        Ok(())
+    }
+
+    // Oldest first, in the order they have to be replayed.
+    pub fn pending(&self) -> Result<Vec<QueuedAction>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id, payload, recorded_at FROM pending_actions ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut actions = vec![];
+        for row in rows {
+            let (id, payload, recorded_at) = row?;
+            match serde_json::from_str(&payload) {
+                Ok(action) => actions.push(QueuedAction {
+                    id,
+                    action,
+                    recorded_at,
+                }),
+                Err(e) => eprintln!("Dropping an unreadable queued action {}: {}", payload, e),
+            }
+        }
+        Ok(actions)
+    }
+
+    pub fn has_pending(&self) -> bool {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM pending_actions", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map_or(false, |v| v > 0)
+    }
+
+    pub fn remove(&self, id: i64) -> Result<(), rusqlite::Error> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM pending_actions WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn put_cache<T: Serialize>(&self, key: &str, value: &T) {
+        let value = serde_json::to_string(value).expect("Unable to serialize JSON");
+        let res = self.conn.lock().unwrap().execute(
+            "INSERT INTO cache (key, value) VALUES (?1, ?2)
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        );
+        if let Err(e) = res {
+            eprintln!("Unable to cache {}: {}", key, e);
+        }
+    }
+
+    pub fn get_cache<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let conn = self.conn.lock().unwrap();
+        let value: Option<String> = conn
+            .query_row(
+                "SELECT value FROM cache WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                eprintln!("Unable to read cache {}: {}", key, e);
+                None
+            });
+        value.and_then(|v| serde_json::from_str(&v).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_in_order() {
+        let store = OfflineStore::open_in_memory().unwrap();
+        assert!(!store.has_pending());
+
+        store.queue(&Action::Start, "2024-07-01 09:00:00").unwrap();
+        store
+            .queue(
+                &Action::SetWorkContent {
+                    project_id: 1,
+                    process_id: 11,
+                    sub_process_ids: vec![],
+                },
+                "2024-07-01 09:00:05",
+            )
+            .unwrap();
+        store.queue(&Action::Stop, "2024-07-01 10:30:00").unwrap();
+        assert!(store.has_pending());
+
+        let pending = store.pending().unwrap();
+        let actions: Vec<Action> = pending.iter().map(|v| v.action.clone()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                Action::Start,
+                Action::SetWorkContent {
+                    project_id: 1,
+                    process_id: 11,
+                    sub_process_ids: vec![],
+                },
+                Action::Stop,
+            ]
+        );
+        assert_eq!(pending[2].recorded_at, "2024-07-01 10:30:00");
+
+        store.remove(pending[0].id).unwrap();
+        assert_eq!(store.pending().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cache_overwrites() {
+        let store = OfflineStore::open_in_memory().unwrap();
+        assert_eq!(store.get_cache::<Vec<u32>>("ids"), None);
+
+        store.put_cache("ids", &vec![1, 2]);
+        store.put_cache("ids", &vec![3]);
+        assert_eq!(store.get_cache::<Vec<u32>>("ids"), Some(vec![3]));
+    }
+}
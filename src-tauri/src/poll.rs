@@ -0,0 +1,47 @@
+// Follows the server's stopwatch, so that changes made on their web app or another device show up here too.
+
+use crate::offline::OfflineStore;
+use crate::settings::Settings;
+use crate::timer::{self, StopWatchState};
+use crowdlog::api::ApiClient;
+use std::{sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+const INTERVAL: Duration = Duration::from_secs(15);
+
+pub fn spawn(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(INTERVAL);
+        let is_logged_in = app
+            .state::<Mutex<Settings>>()
+            .lock()
+            .unwrap()
+            .credentials
+            .is_valid();
+        // The server's stopwatch is outdated until the queued actions are replayed.
+        if !is_logged_in || app.state::<OfflineStore>().has_pending() {
+            continue;
+        }
+
+        let known = app.state::<StopWatchState>().borrow().clone();
+        let api = app.state::<ApiClient>();
+        let sw = match tauri::async_runtime::block_on(api.get_stop_watch()) {
+            Ok(sw) => sw,
+            Err(e) if e.is_unreachable() => continue,
+            Err(e) => {
+                eprintln!("{:#?}", e);
+                continue;
+            }
+        };
+        // An action of the app answered meanwhile, its response is newer than this one.
+        let current = app.state::<StopWatchState>().borrow().clone();
+        let is_outdated = match (&known, &current) {
+            (Some(known), Some(current)) => timer::is_changed(known, current),
+            (known, current) => known.is_some() != current.is_some(),
+        };
+        if !is_outdated {
+            timer::update_state(&app, &sw);
+        }
+    });
+}
@@ -0,0 +1,235 @@
+// Reminds to start the stopwatch when it's not running during the working hours.
+
+use crate::alert;
+use crate::settings::Settings;
+use crate::timer::StopWatchState;
+use crate::utils::TIME_OF_DAY_FORMAT;
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta};
+use crowdlog::api::{ApiClient, StopWatchStatus, WorkContent};
+use serde::{Deserialize, Serialize};
+use std::{sync::Mutex, thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+const INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkingHours {
+    pub enabled: bool,
+    // Days of the week, from Monday (0) to Sunday (6).
+    pub days: Vec<u32>,
+    // `%H:%M`, the end is excluded.
+    pub start: String,
+    pub end: String,
+    // Time without the stopwatch running before reminding.
+    pub reminder_minutes: u32,
+}
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            days: vec![0, 1, 2, 3, 4],
+            start: String::from("09:00"),
+            end: String::from("18:00"),
+            reminder_minutes: 15,
+        }
+    }
+}
+impl WorkingHours {
+    pub fn is_valid(&self) -> bool {
+        let is_in_order = match (self.start_time(), self.end_time()) {
+            (Some(start), Some(end)) => start < end,
+            _ => false,
+        };
+        is_in_order && self.days.iter().all(|v| *v < 7) && self.reminder_minutes > 0
+    }
+
+    fn start_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(&self.start, TIME_OF_DAY_FORMAT).ok()
+    }
+
+    fn end_time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(&self.end, TIME_OF_DAY_FORMAT).ok()
+    }
+
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let (start, end) = match (self.start_time(), self.end_time()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        self.enabled
+            && self.days.contains(&now.weekday().num_days_from_monday())
+            && start <= now.time()
+            && now.time() < end
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StartReminder {
+    minutes: u32,
+    // Last used one, offered to be started. None if there is no history.
+    work_content: Option<WorkContent>,
+}
+
+// Time the stopwatch has been seen not running during the working hours.
+#[derive(Debug, Default)]
+pub struct ReminderTracker {
+    not_running_since: Option<NaiveDateTime>,
+    is_reminded: bool,
+}
+impl ReminderTracker {
+    // True when it's time to remind. Only once until the stopwatch is started, or the working hours are over.
+    pub fn check(&mut self, now: NaiveDateTime, is_clean: bool, hours: &WorkingHours) -> bool {
+        if !is_clean || !hours.contains(now) {
+            *self = Self::default();
+            return false;
+        }
+
+        let not_running_since = *self.not_running_since.get_or_insert(now);
+        let threshold = TimeDelta::minutes(i64::from(hours.reminder_minutes));
+        if self.is_reminded || now - not_running_since < threshold {
+            return false;
+        }
+        self.is_reminded = true;
+        true
+    }
+}
+
+// Checks the stopwatch held by the app every minute during the working hours.
+pub fn spawn(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut tracker = ReminderTracker::default();
+        loop {
+            thread::sleep(INTERVAL);
+            let (hours, is_logged_in) = {
+                let settings = app.state::<Mutex<Settings>>();
+                let settings = settings.lock().unwrap();
+                let hours = settings.preferences.working_hours.clone();
+                (hours, settings.credentials.is_valid())
+            };
+            let now = Local::now().naive_local();
+            if !is_logged_in || !hours.contains(now) {
+                tracker.check(now, false, &hours);
+                continue;
+            }
+            // Including the actions queued while offline. Unknown until it has been fetched.
+            let status = match app.state::<StopWatchState>().borrow().as_ref() {
+                Some(sw) => sw.status.clone(),
+                None => continue,
+            };
+            let is_clean = matches!(status, StopWatchStatus::Clean);
+            if !tracker.check(now, is_clean, &hours) {
+                continue;
+            }
+
+            let api = app.state::<ApiClient>();
+            let work_content = match tauri::async_runtime::block_on(api.get_history()) {
+                Ok(history) => history.into_iter().find(|wc| wc.ids().is_some()),
+                Err(e) => {
+                    eprintln!("{:#?}", e);
+                    None
+                }
+            };
+            remind(
+                &app,
+                &StartReminder {
+                    minutes: hours.reminder_minutes,
+                    work_content,
+                },
+            );
+        }
+    });
+}
+
+// Emits a `start_reminder` event with the notification, to let the user start the stopwatch from the window.
+fn remind(app: &AppHandle, reminder: &StartReminder) {
+    let body = match reminder.work_content.as_ref().and_then(|wc| wc.names()) {
+        Some((project, process)) => format!(
+            "The stopwatch is not running. Start {} ▶ {}?",
+            project, process
+        ),
+        None => String::from("The stopwatch is not running."),
+    };
+    alert::show_notification(app, &body);
+    app.emit_all("start_reminder", reminder)
+        .expect("failed to emit start_reminder event");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crowdlog::utils::TIME_FORMAT;
+
+    fn time(str: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT).unwrap()
+    }
+
+    fn hours() -> WorkingHours {
+        WorkingHours {
+            enabled: true,
+            ..WorkingHours::default()
+        }
+    }
+
+    #[test]
+    fn working_hours() {
+        let hours = hours();
+        // 2024-07-01 is a Monday
+        assert!(!hours.contains(time("2024-07-01 08:59:59")));
+        assert!(hours.contains(time("2024-07-01 09:00:00")));
+        assert!(hours.contains(time("2024-07-05 17:59:59")));
+        assert!(!hours.contains(time("2024-07-05 18:00:00")));
+        assert!(!hours.contains(time("2024-07-06 10:00:00")));
+
+        let disabled = WorkingHours::default();
+        assert!(!disabled.contains(time("2024-07-01 10:00:00")));
+    }
+
+    #[test]
+    fn remind_once_when_not_started() {
+        let hours = hours();
+        let mut tracker = ReminderTracker::default();
+        assert!(!tracker.check(time("2024-07-01 08:50:00"), true, &hours));
+        assert!(!tracker.check(time("2024-07-01 09:00:00"), true, &hours));
+        assert!(!tracker.check(time("2024-07-01 09:14:00"), true, &hours));
+        assert!(tracker.check(time("2024-07-01 09:15:00"), true, &hours));
+        assert!(!tracker.check(time("2024-07-01 09:16:00"), true, &hours));
+
+        // Started, then stopped again
+        assert!(!tracker.check(time("2024-07-01 10:00:00"), false, &hours));
+        assert!(!tracker.check(time("2024-07-01 12:00:00"), true, &hours));
+        assert!(tracker.check(time("2024-07-01 12:15:00"), true, &hours));
+
+        // Next day
+        assert!(!tracker.check(time("2024-07-01 18:00:00"), true, &hours));
+        assert!(!tracker.check(time("2024-07-02 09:00:00"), true, &hours));
+        assert!(tracker.check(time("2024-07-02 09:15:00"), true, &hours));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(hours().is_valid());
+        let invalid = [
+            WorkingHours {
+                start: String::from("18:00"),
+                end: String::from("09:00"),
+                ..hours()
+            },
+            WorkingHours {
+                end: String::from("6pm"),
+                ..hours()
+            },
+            WorkingHours {
+                days: vec![7],
+                ..hours()
+            },
+            WorkingHours {
+                reminder_minutes: 0,
+                ..hours()
+            },
+        ];
+        for hours in invalid.iter() {
+            assert!(!hours.is_valid(), "{:?}", hours);
+        }
+    }
+}
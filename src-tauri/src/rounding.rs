@@ -0,0 +1,121 @@
+// Rounding of the recorded time, applied when a stopwatch is applied as a timesheet entry.
+
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+
+pub const UNIT_MINUTES: [u32; 5] = [1, 5, 10, 15, 30];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    // Keep the duration Crowdlog computes.
+    Off,
+    Up,
+    Down,
+    Nearest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Rounding {
+    pub mode: RoundingMode,
+    pub unit_minutes: u32,
+}
+impl Default for Rounding {
+    fn default() -> Self {
+        Self {
+            mode: RoundingMode::Off,
+            unit_minutes: 1,
+        }
+    }
+}
+impl Rounding {
+    pub fn is_valid(&self) -> bool {
+        UNIT_MINUTES.contains(&self.unit_minutes)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != RoundingMode::Off
+    }
+
+    pub fn apply(&self, duration: TimeDelta) -> TimeDelta {
+        let unit = i64::from(self.unit_minutes) * 60;
+        let secs = duration.num_seconds();
+        let units = match self.mode {
+            RoundingMode::Off => return duration,
+            RoundingMode::Up => (secs + unit - 1).div_euclid(unit),
+            RoundingMode::Down => secs.div_euclid(unit),
+            RoundingMode::Nearest => (secs + unit / 2).div_euclid(unit),
+        };
+        TimeDelta::seconds(units * unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(m: i64, s: i64) -> TimeDelta {
+        TimeDelta::seconds(m * 60 + s)
+    }
+
+    fn round(mode: RoundingMode, unit_minutes: u32, duration: TimeDelta) -> TimeDelta {
+        Rounding { mode, unit_minutes }.apply(duration)
+    }
+
+    #[test]
+    fn off_keeps_duration() {
+        assert_eq!(round(RoundingMode::Off, 15, minutes(7, 31)), minutes(7, 31));
+    }
+
+    #[test]
+    fn round_up() {
+        assert_eq!(round(RoundingMode::Up, 1, minutes(7, 1)), minutes(8, 0));
+        assert_eq!(round(RoundingMode::Up, 15, minutes(15, 0)), minutes(15, 0));
+        assert_eq!(round(RoundingMode::Up, 15, minutes(15, 1)), minutes(30, 0));
+        assert_eq!(round(RoundingMode::Up, 30, minutes(0, 0)), minutes(0, 0));
+    }
+
+    #[test]
+    fn round_down() {
+        assert_eq!(round(RoundingMode::Down, 1, minutes(7, 59)), minutes(7, 0));
+        assert_eq!(
+            round(RoundingMode::Down, 10, minutes(19, 59)),
+            minutes(10, 0)
+        );
+        assert_eq!(round(RoundingMode::Down, 5, minutes(4, 59)), minutes(0, 0));
+    }
+
+    #[test]
+    fn round_nearest() {
+        assert_eq!(
+            round(RoundingMode::Nearest, 5, minutes(7, 29)),
+            minutes(5, 0)
+        );
+        assert_eq!(
+            round(RoundingMode::Nearest, 5, minutes(7, 30)),
+            minutes(10, 0)
+        );
+        assert_eq!(
+            round(RoundingMode::Nearest, 30, minutes(74, 59)),
+            minutes(60, 0)
+        );
+        assert_eq!(
+            round(RoundingMode::Nearest, 30, minutes(75, 0)),
+            minutes(90, 0)
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert!(Rounding {
+            mode: RoundingMode::Up,
+            unit_minutes: 15
+        }
+        .is_valid());
+        assert!(!Rounding {
+            mode: RoundingMode::Up,
+            unit_minutes: 7
+        }
+        .is_valid());
+    }
+}
@@ -0,0 +1,281 @@
+// In-memory index of every project and its processes, to find a work content by typing a few letters of both.
+
+use crate::tray;
+use crowdlog::api::{ApiClient, Process, Project, ReqError};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+
+pub const DEFAULT_LIMIT: usize = 20;
+// Projects whose processes are fetched at the same time, not to flood Crowdlog with requests.
+const CONCURRENT_FETCHES: usize = 4;
+
+pub type SearchHandle = Mutex<SearchIndex>;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SearchResult {
+    pub project: Project,
+    pub process: Process,
+    score: u32,
+}
+
+// Not built until `new`, while it's empty until the projects have been fetched.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    entries: Vec<(Project, Process)>,
+    is_built: bool,
+}
+impl SearchIndex {
+    pub fn new(entries: Vec<(Project, Process)>) -> Self {
+        Self {
+            entries,
+            is_built: true,
+        }
+    }
+
+    // A built index can still be empty, e.g. for a user without any project.
+    pub fn is_built(&self) -> bool {
+        self.is_built
+    }
+
+    // Every project with its processes, in the order they have been fetched.
+    pub fn projects(&self) -> Vec<(Project, Vec<Process>)> {
+        let mut projects: Vec<(Project, Vec<Process>)> = vec![];
+        for (project, process) in self.entries.iter() {
+            match projects.last_mut() {
+                Some((pj, processes)) if pj.id == project.id => processes.push(process.clone()),
+                _ => projects.push((project.clone(), vec![process.clone()])),
+            }
+        }
+        projects
+    }
+
+    // Every word of the query has to match the project or the process name, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words.is_empty() {
+            return vec![];
+        }
+
+        let mut results: Vec<SearchResult> = self
+            .entries
+            .iter()
+            .filter_map(|(project, process)| {
+                let project_name = project.name.to_lowercase();
+                let process_name = process.name.to_lowercase();
+                let mut score = 0;
+                for word in words.iter() {
+                    let best = match (score_of(word, &project_name), score_of(word, &process_name))
+                    {
+                        (Some(a), Some(b)) => a.max(b),
+                        (a, b) => a.or(b)?,
+                    };
+                    score += best;
+                }
+                Some(SearchResult {
+                    project: project.clone(),
+                    process: process.clone(),
+                    score,
+                })
+            })
+            .collect();
+
+        // Shorter names first among equal scores, they are closer to what has been typed.
+        results.sort_by_key(|v| {
+            (
+                std::cmp::Reverse(v.score),
+                v.project.name.chars().count() + v.process.name.chars().count(),
+            )
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+// Score of a lowercase word in a lowercase name, None if its letters don't appear in order.
+// Substrings beat scattered letters, and matches at the start of a word beat the ones inside.
+fn score_of(word: &str, name: &str) -> Option<u32> {
+    if let Some(pos) = name.find(word) {
+        let at_word_start = pos == 0 || !name[..pos].ends_with(char::is_alphanumeric);
+        let len = word.chars().count() as u32;
+        return Some(len * 10 + if at_word_start { 50 } else { 20 });
+    }
+
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut is_consecutive = false;
+    let mut chars = word.chars().peekable();
+    for c in name.chars() {
+        let target = match chars.peek() {
+            Some(v) => *v,
+            None => break,
+        };
+        if c == target {
+            score += 5;
+            if is_consecutive {
+                score += 3;
+            }
+            if prev.map_or(true, |p| !p.is_alphanumeric()) {
+                score += 4;
+            }
+            is_consecutive = true;
+            chars.next();
+        } else {
+            is_consecutive = false;
+        }
+        prev = Some(c);
+    }
+    match chars.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+// Fetches every project and its level 1 processes, the processes of a few projects at once.
+// A project whose processes can't be fetched is left out, rather than the whole index.
+pub async fn build(app: &AppHandle) -> Result<SearchIndex, ReqError> {
+    let api = app.state::<ApiClient>();
+    let user = api.get_user().await?;
+    let projects = api.get_projects(user.id).await?;
+
+    let permits = Arc::new(Semaphore::new(CONCURRENT_FETCHES));
+    let tasks: Vec<_> = projects
+        .into_iter()
+        .map(|project| {
+            let app = app.clone();
+            let permits = Arc::clone(&permits);
+            tauri::async_runtime::spawn(async move {
+                // Only fails once closed, which it never is.
+                let _permit = permits.acquire_owned().await;
+                let api = app.state::<ApiClient>();
+                let processes = api.get_processes(user.id, project.id, 1, None).await;
+                (project, processes)
+            })
+        })
+        .collect();
+
+    let mut entries = vec![];
+    for task in tasks {
+        match task.await {
+            Ok((project, Ok(processes))) => {
+                entries.extend(processes.into_iter().map(|pc| (project.clone(), pc)));
+            }
+            Ok((project, Err(e))) => {
+                eprintln!("Leaving {} out of the search: {:#?}", project.name, e);
+            }
+            Err(e) => eprintln!("{:#?}", e),
+        }
+    }
+    Ok(SearchIndex::new(entries))
+}
+
+// Rebuilds the index in the background, e.g. after the projects have been fetched again.
+// The projects menu of the tray is built from it.
+pub fn refresh(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match build(&app).await {
+            Ok(index) => {
+                *app.state::<SearchHandle>().lock().unwrap() = index;
+                tray::refresh_menu(&app);
+            }
+            Err(e) => eprintln!("{:#?}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> SearchIndex {
+        let entry = |pj: (u32, &str), pc: (u32, &str)| {
+            (
+                Project {
+                    id: pj.0,
+                    name: pj.1.to_string(),
+                },
+                Process {
+                    id: pc.0,
+                    name: pc.1.to_string(),
+                },
+            )
+        };
+        let acme = (1, "Acme Corp");
+        let globex = (2, "Globex");
+        SearchIndex::new(vec![
+            entry(acme, (11, "Design")),
+            entry(acme, (12, "Development")),
+            entry(acme, (13, "Graphic design review")),
+            entry(globex, (21, "Meeting")),
+            entry(globex, (22, "Support")),
+        ])
+    }
+
+    fn names(results: &[SearchResult]) -> Vec<(&str, &str)> {
+        results
+            .iter()
+            .map(|v| (v.project.name.as_str(), v.process.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn match_project_and_process() {
+        let results = index().search("acme design", DEFAULT_LIMIT);
+        assert_eq!(
+            names(&results),
+            vec![
+                ("Acme Corp", "Design"),
+                ("Acme Corp", "Graphic design review")
+            ]
+        );
+    }
+
+    #[test]
+    fn match_scattered_letters() {
+        let results = index().search("glb mtg", DEFAULT_LIMIT);
+        assert_eq!(names(&results), vec![("Globex", "Meeting")]);
+    }
+
+    #[test]
+    fn word_order_and_case_do_not_matter() {
+        let results = index().search("SUPPORT glo", DEFAULT_LIMIT);
+        assert_eq!(names(&results), vec![("Globex", "Support")]);
+    }
+
+    #[test]
+    fn no_match() {
+        let index = index();
+        assert!(index.search("initech", DEFAULT_LIMIT).is_empty());
+        assert!(index.search("   ", DEFAULT_LIMIT).is_empty());
+    }
+
+    #[test]
+    fn built_even_without_any_project() {
+        assert!(!SearchIndex::default().is_built());
+        assert!(SearchIndex::new(vec![]).is_built());
+    }
+
+    #[test]
+    fn group_processes_by_project() {
+        let projects = index().projects();
+        let names: Vec<(&str, usize)> = projects
+            .iter()
+            .map(|(pj, processes)| (pj.name.as_str(), processes.len()))
+            .collect();
+        assert_eq!(names, vec![("Acme Corp", 3), ("Globex", 2)]);
+    }
+
+    #[test]
+    fn limit_results() {
+        let results = index().search("acme", 2);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn substring_beats_scattered_letters() {
+        assert!(score_of("des", "design") > score_of("dsg", "design"));
+        assert!(score_of("des", "design") > score_of("sig", "design"));
+        assert_eq!(score_of("xyz", "design"), None);
+    }
+}
@@ -0,0 +1,133 @@
+// Global keyboard shortcuts to control the stopwatch without bringing the window to the front.
+
+use crate::tray;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+
+// Emitted to the window to open the search page of the work contents.
+const OPEN_SWITCHER: &str = "open_switcher";
+
+// Accelerators such as `CmdOrCtrl+Alt+S`, see https://v1.tauri.app/v1/api/js/globalshortcut/.
+// Empty ones are not registered.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Shortcuts {
+    #[serde(default)]
+    pub start: String,
+    #[serde(default)]
+    pub stop: String,
+    #[serde(default)]
+    pub cancel: String,
+    #[serde(default)]
+    pub switcher: String,
+}
+impl Shortcuts {
+    fn bindings(&self) -> Vec<(&str, Action)> {
+        [
+            (&self.start, Action::Start),
+            (&self.stop, Action::Stop),
+            (&self.cancel, Action::Cancel),
+            (&self.switcher, Action::Switcher),
+        ]
+        .into_iter()
+        .map(|(accelerator, action)| (accelerator.trim(), action))
+        .filter(|(accelerator, _)| !accelerator.is_empty())
+        .collect()
+    }
+
+    // The first accelerator bound to several actions. Modifiers are compared regardless of case and order.
+    pub fn duplicate(&self) -> Option<&str> {
+        let bindings = self.bindings();
+        let keys: Vec<Vec<String>> = bindings.iter().map(|(v, _)| normalize(v)).collect();
+        (0..keys.len())
+            .find(|&i| keys[i + 1..].contains(&keys[i]))
+            .map(|i| bindings[i].0)
+    }
+}
+
+fn normalize(accelerator: &str) -> Vec<String> {
+    let mut keys: Vec<String> = accelerator
+        .split('+')
+        .map(|v| v.trim().to_lowercase())
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Start,
+    Stop,
+    Cancel,
+    Switcher,
+}
+
+// Replaces the registered shortcuts with the given ones.
+pub fn register(app: &AppHandle, shortcuts: &Shortcuts) -> Result<(), tauri::Error> {
+    let mut manager = app.global_shortcut_manager();
+    manager.unregister_all()?;
+    for (accelerator, action) in shortcuts.bindings() {
+        let app = app.clone();
+        manager.register(accelerator, move || on_shortcut(&app, action))?;
+    }
+    Ok(())
+}
+
+fn on_shortcut(app: &AppHandle, action: Action) {
+    let id = match action {
+        Action::Start => tray::START,
+        Action::Stop => tray::STOP,
+        Action::Cancel => tray::CANCEL,
+        Action::Switcher => {
+            tray::show_window(app);
+            app.emit_all(OPEN_SWITCHER, ())
+                .expect("failed to emit open_switcher event");
+            return;
+        }
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = tray::run_timer_action(&app, id).await {
+            eprintln!("{:#?}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcuts(start: &str, stop: &str, cancel: &str, switcher: &str) -> Shortcuts {
+        Shortcuts {
+            start: start.to_string(),
+            stop: stop.to_string(),
+            cancel: cancel.to_string(),
+            switcher: switcher.to_string(),
+        }
+    }
+
+    #[test]
+    fn empty_ones_are_not_bound() {
+        let shortcuts = shortcuts("CmdOrCtrl+Alt+S", "", " ", "CmdOrCtrl+Alt+Space");
+        assert_eq!(
+            shortcuts.bindings(),
+            vec![
+                ("CmdOrCtrl+Alt+S", Action::Start),
+                ("CmdOrCtrl+Alt+Space", Action::Switcher)
+            ]
+        );
+    }
+
+    #[test]
+    fn find_duplicate() {
+        assert_eq!(shortcuts("", "", "", "").duplicate(), None);
+        assert_eq!(
+            shortcuts("CmdOrCtrl+Alt+S", "CmdOrCtrl+Alt+E", "", "").duplicate(),
+            None
+        );
+        assert_eq!(
+            shortcuts("CmdOrCtrl+Alt+S", "", "alt+cmdorctrl+s", "").duplicate(),
+            Some("CmdOrCtrl+Alt+S")
+        );
+    }
+}
@@ -0,0 +1,219 @@
+// Totals of the recorded time of a day or a week, grouped by project and process.
+
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use crowdlog::api::Timesheet;
+use crowdlog::utils::DATE_FORMAT;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    // From Monday to Sunday.
+    Week,
+}
+impl Period {
+    pub fn range(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Day => (date, date),
+            Period::Week => {
+                let monday =
+                    date - TimeDelta::days(i64::from(date.weekday().num_days_from_monday()));
+                (monday, monday + TimeDelta::days(6))
+            }
+        }
+    }
+}
+
+// Durations are in minutes.
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct Summary {
+    start_date: String,
+    end_date: String,
+    total: u32,
+    // Every day of the period, including the ones without any entry.
+    days: Vec<DayTotal>,
+    // Longest first.
+    projects: Vec<ProjectTotal>,
+}
+
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct DayTotal {
+    date: String,
+    total: u32,
+}
+
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct ProjectTotal {
+    id: u32,
+    name: String,
+    total: u32,
+    processes: Vec<ProcessTotal>,
+}
+
+#[derive(Debug, serde::Serialize, PartialEq)]
+pub struct ProcessTotal {
+    id: u32,
+    name: String,
+    total: u32,
+}
+
+pub fn summarize(start: NaiveDate, end: NaiveDate, timesheets: &[Timesheet]) -> Summary {
+    let mut days = BTreeMap::new();
+    let mut date = start;
+    while date <= end {
+        days.insert(date.format(DATE_FORMAT).to_string(), 0);
+        date += TimeDelta::days(1);
+    }
+
+    let mut projects: Vec<ProjectTotal> = vec![];
+    for t in timesheets.iter() {
+        if let Some(total) = days.get_mut(&t.date) {
+            *total += t.duration;
+        } else {
+            // Out of the period, the server should not have sent it.
+            continue;
+        }
+
+        // Entries without a work content are only counted in the totals of the day.
+        let (ids, names) = match (t.work_content.ids(), t.work_content.names()) {
+            (Some(ids), Some(names)) => (ids, names),
+            _ => continue,
+        };
+        let project = match projects.iter_mut().position(|v| v.id == ids.0) {
+            Some(i) => &mut projects[i],
+            None => {
+                projects.push(ProjectTotal {
+                    id: ids.0,
+                    name: names.0.to_string(),
+                    total: 0,
+                    processes: vec![],
+                });
+                projects.last_mut().unwrap()
+            }
+        };
+        project.total += t.duration;
+        match project.processes.iter_mut().find(|v| v.id == ids.1) {
+            Some(process) => process.total += t.duration,
+            None => project.processes.push(ProcessTotal {
+                id: ids.1,
+                name: names.1.to_string(),
+                total: t.duration,
+            }),
+        }
+    }
+
+    // Stable sorts, so equal totals keep the order of the entries.
+    projects.sort_by_key(|v| Reverse(v.total));
+    for project in projects.iter_mut() {
+        project.processes.sort_by_key(|v| Reverse(v.total));
+    }
+
+    Summary {
+        start_date: start.format(DATE_FORMAT).to_string(),
+        end_date: end.format(DATE_FORMAT).to_string(),
+        total: days.values().sum(),
+        days: days
+            .into_iter()
+            .map(|(date, total)| DayTotal { date, total })
+            .collect(),
+        projects,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crowdlog::api::{Process, Project, WorkContent};
+
+    fn date(str: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(str, DATE_FORMAT).unwrap()
+    }
+
+    fn entry(date: &str, project: (u32, &str), process: (u32, &str), duration: u32) -> Timesheet {
+        Timesheet {
+            id: 0,
+            date: date.to_string(),
+            start_at: None,
+            end_at: None,
+            duration,
+            memo: String::new(),
+            work_content: WorkContent::new(
+                Project {
+                    id: project.0,
+                    name: project.1.to_string(),
+                },
+                Process {
+                    id: process.0,
+                    name: process.1.to_string(),
+                },
+            ),
+        }
+    }
+
+    #[test]
+    fn week_range() {
+        // 2024-07-03 is a Wednesday
+        assert_eq!(
+            Period::Week.range(date("2024-07-03")),
+            (date("2024-07-01"), date("2024-07-07"))
+        );
+        assert_eq!(
+            Period::Week.range(date("2024-07-07")),
+            (date("2024-07-01"), date("2024-07-07"))
+        );
+        assert_eq!(
+            Period::Day.range(date("2024-07-03")),
+            (date("2024-07-03"), date("2024-07-03"))
+        );
+    }
+
+    #[test]
+    fn group_by_project_and_process() {
+        let acme = (1, "Acme Corp");
+        let globex = (2, "Globex");
+        let timesheets = vec![
+            entry("2024-07-01", acme, (11, "Design"), 60),
+            entry("2024-07-01", globex, (21, "Meeting"), 30),
+            entry("2024-07-02", acme, (12, "Development"), 120),
+            entry("2024-07-02", acme, (11, "Design"), 15),
+            entry("2024-07-03", globex, (21, "Meeting"), 45),
+        ];
+
+        let summary = summarize(date("2024-07-01"), date("2024-07-02"), &timesheets);
+        assert_eq!(summary.total, 225);
+        assert_eq!(
+            summary.days,
+            vec![
+                DayTotal {
+                    date: String::from("2024-07-01"),
+                    total: 90
+                },
+                DayTotal {
+                    date: String::from("2024-07-02"),
+                    total: 135
+                },
+            ]
+        );
+
+        assert_eq!(summary.projects.len(), 2);
+        let acme = &summary.projects[0];
+        assert_eq!((acme.name.as_str(), acme.total), ("Acme Corp", 195));
+        let processes: Vec<(&str, u32)> = acme
+            .processes
+            .iter()
+            .map(|v| (v.name.as_str(), v.total))
+            .collect();
+        assert_eq!(processes, vec![("Development", 120), ("Design", 75)]);
+        assert_eq!(summary.projects[1].total, 30);
+    }
+
+    #[test]
+    fn empty_days_are_listed() {
+        let summary = summarize(date("2024-07-01"), date("2024-07-07"), &[]);
+        assert_eq!(summary.days.len(), 7);
+        assert_eq!(summary.total, 0);
+        assert!(summary.projects.is_empty());
+    }
+}
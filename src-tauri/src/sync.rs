@@ -0,0 +1,350 @@
+// Replays the stopwatch actions queued while Crowdlog was unreachable, once it's reachable again.
+
+use crate::offline::{Action, OfflineStore, QueuedAction};
+use crate::rounding::Rounding;
+use crate::{timer, tray};
+use crowdlog::api::{ApiClient, ReqError, StopWatch, StopWatchStatus};
+use std::{thread, time::Duration};
+use tauri::{AppHandle, Manager};
+
+const INTERVAL: Duration = Duration::from_secs(30);
+
+// An action that couldn't be replayed as it was done, because the stopwatch has been changed elsewhere meanwhile.
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SyncConflict {
+    action: QueuedAction,
+    reason: String,
+}
+
+pub fn spawn(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(INTERVAL);
+        if !app.state::<OfflineStore>().has_pending() {
+            continue;
+        }
+        match tauri::async_runtime::block_on(replay(&app)) {
+            Ok(()) => {}
+            // Still offline, retried on the next round
+            Err(e) if e.is_unreachable() => {}
+            Err(e) => eprintln!("{:#?}", e),
+        }
+    });
+}
+
+async fn replay(app: &AppHandle) -> Result<(), ReqError> {
+    let api = app.state::<ApiClient>();
+    let store = app.state::<OfflineStore>();
+    let conflicts = replay_pending(&api, &store, &timer::rounding(app)).await?;
+
+    let sw = api.get_stop_watch().await?;
+    timer::update_state(app, &sw);
+    tray::refresh_menu(app);
+    if !conflicts.is_empty() {
+        app.emit_all("sync_conflicts", &conflicts)
+            .expect("failed to emit sync_conflicts event");
+    }
+    Ok(())
+}
+
+// Replays the queued actions in order and removes them, returning the ones that conflicted.
+async fn replay_pending(
+    api: &ApiClient,
+    store: &OfflineStore,
+    rounding: &Rounding,
+) -> Result<Vec<SyncConflict>, ReqError> {
+    let pending = store.pending().map_err(|e| ReqError {
+        status: 500,
+        message: String::from("Unable to read the queued actions"),
+        source: Some(e.to_string()),
+    })?;
+
+    let mut conflicts = vec![];
+    // A start followed by its stop, both done offline. It stays queued until the stop is replayed.
+    let mut offline_start: Option<&QueuedAction> = None;
+    for (i, queued) in pending.iter().enumerate() {
+        // The server's state decides whether the action still makes sense.
+        let sw = api.get_stop_watch().await?;
+        if queued.action == Action::Start
+            && matches!(sw.status, StopWatchStatus::Clean)
+            && is_stopped_later(&pending[i + 1..])
+        {
+            offline_start = Some(queued);
+            continue;
+        }
+
+        let res = match (&queued.action, offline_start) {
+            (Action::Stop, Some(start)) => replay_run(api, &sw, start, queued, rounding).await,
+            _ => replay_action(api, &sw, queued, rounding).await,
+        };
+        let reason = match res {
+            Ok(reason) => reason,
+            Err(e) if e.is_unreachable() => return Err(e),
+            // Rejected by the server, retrying won't help.
+            Err(e) => Some(e.message),
+        };
+        if let Some(reason) = reason {
+            conflicts.push(SyncConflict {
+                action: queued.clone(),
+                reason,
+            });
+        }
+
+        let mut replayed = vec![queued];
+        if queued.action == Action::Stop {
+            replayed.extend(offline_start.take());
+        }
+        for v in replayed {
+            if let Err(e) = store.remove(v.id) {
+                eprintln!("Unable to remove the replayed action: {}", e);
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+// Whether the next action starting or ending the stopwatch is a stop.
+fn is_stopped_later(rest: &[QueuedAction]) -> bool {
+    rest.iter()
+        .map(|v| &v.action)
+        .find(|v| matches!(v, Action::Start | Action::Stop | Action::Cancel))
+        == Some(&Action::Stop)
+}
+
+// Crowdlog can't start a stopwatch in the past, so the time from an offline start to its stop is recorded
+// as a single entry instead. Its work content is the one of the stopwatch, set by the actions in between if any.
+async fn replay_run(
+    api: &ApiClient,
+    sw: &StopWatch,
+    start: &QueuedAction,
+    stop: &QueuedAction,
+    rounding: &Rounding,
+) -> Result<Option<String>, ReqError> {
+    match timer::replayed_entry(sw, &start.recorded_at, &stop.recorded_at, rounding) {
+        Some(entry) => {
+            api.create_timesheet(&entry).await?;
+            Ok(None)
+        }
+        None => Ok(Some(String::from(
+            "Stopwatch had no work content to record",
+        ))),
+    }
+}
+
+// Returns the reason when the action conflicts with the server's stopwatch.
+async fn replay_action(
+    api: &ApiClient,
+    sw: &StopWatch,
+    queued: &QueuedAction,
+    rounding: &Rounding,
+) -> Result<Option<String>, ReqError> {
+    match (&queued.action, &sw.status) {
+        (Action::Start, StopWatchStatus::Clean) => {
+            let started = api.start_timer(sw.id).await?;
+            Ok(Some(format!(
+                "Started at {} instead of {}",
+                started.start_at, queued.recorded_at
+            )))
+        }
+        (Action::Start, StopWatchStatus::Started) => Ok(Some(String::from(
+            "Stopwatch was already started on the server",
+        ))),
+        (Action::Start, StopWatchStatus::NeedToApply) => Ok(Some(String::from(
+            "Stopwatch was stopped without applying on the server",
+        ))),
+        (Action::Stop, StopWatchStatus::Started | StopWatchStatus::NeedToApply)
+            if is_started_after(sw, &queued.recorded_at) =>
+        {
+            Ok(Some(format!(
+                "Stopwatch was started again on the server at {}",
+                sw.start_at
+            )))
+        }
+        (Action::Stop, StopWatchStatus::Started | StopWatchStatus::NeedToApply) => {
+            let stopped = match sw.status {
+                StopWatchStatus::Started => api.stop_timer(sw.id).await?,
+                _ => sw.clone(),
+            };
+            // Recorded until the action was done, not until it's replayed.
+            let end_at = timer::replayed_end_at(&stopped, &queued.recorded_at, rounding);
+            timer::apply(api, &stopped, end_at.as_deref()).await?;
+            api.reset_timer(sw.id).await?;
+            Ok(None)
+        }
+        (Action::Stop, StopWatchStatus::Clean) => Ok(Some(String::from(
+            "Stopwatch was not running on the server",
+        ))),
+        (Action::Cancel, StopWatchStatus::Clean) => Ok(None),
+        (Action::Cancel, _) => {
+            api.reset_timer(sw.id).await?;
+            Ok(None)
+        }
+        (
+            Action::SetWorkContent {
+                project_id,
+                process_id,
+                sub_process_ids,
+            },
+            _,
+        ) => {
+            let mut process_ids = vec![*process_id];
+            process_ids.extend(sub_process_ids);
+            api.add_work_content_to_history(*project_id, &process_ids)
+                .await?;
+            let memo = sw.work_content.memo();
+            api.set_work_content(sw.id, *project_id, &process_ids, memo)
+                .await?;
+            Ok(None)
+        }
+        (Action::SetMemo { memo }, _) => {
+            let project_id = sw.work_content.ids().map_or(0, |(id, _)| id);
+            let process_ids = sw.work_content.process_ids();
+            api.set_work_content(sw.id, project_id, &process_ids, memo)
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+// Whether the stopwatch has been started after `at`, e.g. restarted elsewhere once stopped here.
+fn is_started_after(sw: &StopWatch, at: &str) -> bool {
+    match (timer::parse_time(&sw.start_at), timer::parse_time(at)) {
+        (Some(start), Some(at)) => start > at,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crowdlog::mock_server::{self, block_on, MockServer};
+    use crowdlog::Credentials;
+    use serde_json::json;
+
+    fn logged_in_client(server: &MockServer) -> ApiClient {
+        let api = ApiClient::new(server.base_url());
+        block_on(api.login(&Credentials {
+            email: String::from(mock_server::EMAIL),
+            passwd: String::from(mock_server::PASSWD),
+        }))
+        .unwrap();
+        api
+    }
+
+    fn store(actions: &[(Action, &str)]) -> OfflineStore {
+        let store = OfflineStore::open_in_memory().unwrap();
+        for (action, recorded_at) in actions.iter() {
+            store.queue(action, recorded_at).unwrap();
+        }
+        store
+    }
+
+    fn running_since(server: &MockServer, api: &ApiClient, start_at: &str) {
+        block_on(api.set_work_content(mock_server::STOP_WATCH_ID, 1, &[12], "API")).unwrap();
+        let mut state = server.state();
+        state.stop_watch["status"] = json!("timing");
+        state.stop_watch["start_at"] = json!(start_at);
+    }
+
+    #[test]
+    fn replay_offline_start_and_stop_as_a_single_entry() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+        let store = store(&[
+            (Action::Start, "2024-07-01 09:00:00"),
+            (
+                Action::SetWorkContent {
+                    project_id: 1,
+                    process_id: 12,
+                    sub_process_ids: vec![122],
+                },
+                "2024-07-01 09:00:05",
+            ),
+            (
+                Action::SetMemo {
+                    memo: String::from("Login form"),
+                },
+                "2024-07-01 09:01:00",
+            ),
+            (Action::Stop, "2024-07-01 10:29:40"),
+        ]);
+        let rounding = Rounding {
+            mode: crate::rounding::RoundingMode::Nearest,
+            unit_minutes: 15,
+        };
+
+        let conflicts = block_on(replay_pending(&api, &store, &rounding)).unwrap();
+        assert!(conflicts.is_empty());
+        assert!(!store.has_pending());
+
+        let state = server.state();
+        assert_eq!(state.stop_watch["status"], "reset");
+        assert_eq!(state.timesheets.len(), 1);
+        let entry = &state.timesheets[0];
+        assert_eq!(entry["start_at"], "2024-07-01 09:00:00");
+        assert_eq!(entry["end_at"], "2024-07-01 10:30:00");
+        assert_eq!(entry["duration"], 90);
+        assert_eq!(entry["memo"], "Login form");
+        assert_eq!(entry["work_content"]["process_2"]["id"], 122);
+        assert_eq!(state.history[0]["work_content"]["process_2"]["id"], 122);
+    }
+
+    #[test]
+    fn replay_stop_until_it_was_done() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+        running_since(&server, &api, "2024-07-01 09:00:00");
+        let store = store(&[(Action::Stop, "2024-07-01 10:30:00")]);
+
+        let conflicts = block_on(replay_pending(&api, &store, &Rounding::default())).unwrap();
+        assert!(conflicts.is_empty());
+
+        let state = server.state();
+        assert_eq!(state.stop_watch["status"], "reset");
+        assert_eq!(state.timesheets.len(), 1);
+        assert_eq!(state.timesheets[0]["end_at"], "2024-07-01 10:30:00");
+        assert_eq!(state.timesheets[0]["duration"], 90);
+    }
+
+    #[test]
+    fn stop_conflicts_with_a_restart_elsewhere() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+        running_since(&server, &api, "2024-07-01 10:00:00");
+        let store = store(&[(Action::Stop, "2024-07-01 09:30:00")]);
+
+        let conflicts = block_on(replay_pending(&api, &store, &Rounding::default())).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].action.action, Action::Stop);
+        assert!(!store.has_pending());
+
+        // Left running as it is
+        let state = server.state();
+        assert_eq!(state.stop_watch["status"], "timing");
+        assert_eq!(state.stop_watch["start_at"], "2024-07-01 10:00:00");
+        assert!(state.timesheets.is_empty());
+    }
+
+    #[test]
+    fn start_without_stop_is_started_on_replay() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+        block_on(api.set_work_content(mock_server::STOP_WATCH_ID, 2, &[21], "")).unwrap();
+        let store = store(&[
+            (Action::Start, "2024-07-01 09:00:00"),
+            (Action::Stop, "2024-07-01 09:30:00"),
+            (Action::Start, "2024-07-01 10:00:00"),
+        ]);
+
+        let conflicts = block_on(replay_pending(&api, &store, &Rounding::default())).unwrap();
+        assert!(!store.has_pending());
+        // Recorded before the last start, which can only be done now
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].action.recorded_at, "2024-07-01 10:00:00");
+
+        let state = server.state();
+        assert_eq!(state.stop_watch["status"], "timing");
+        assert_eq!(state.timesheets.len(), 1);
+        assert_eq!(state.timesheets[0]["end_at"], "2024-07-01 09:30:00");
+    }
+}
@@ -0,0 +1,119 @@
+// Ticks every second while the stopwatch is running, as a task following the stopwatch held by the app.
+// The elapsed time is published to the rest of the app, and to the windows as `timer_tick` events.
+
+use crate::alert::{self, AlertTracker};
+use crate::idle::IdleHandle;
+use crate::settings::Settings;
+use crate::timer::{self, StopWatchState, ToClockStr};
+use crate::tray;
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, Utc};
+use crowdlog::utils;
+use std::{sync::Mutex, time::Duration};
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+use tokio::time::{timeout_at, Instant};
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+// Time the stopwatch has been running as of the last tick, None while it's not running.
+pub type Elapsed = watch::Sender<Option<TimeDelta>>;
+
+// A single run of the stopwatch, kept as long as its start time doesn't change.
+struct Run {
+    // As given by Crowdlog, in the time zone of the tenant.
+    start: NaiveDateTime,
+    started: DateTime<Utc>,
+    // On the clock of this machine, as the alert preferences are.
+    alerts: AlertTracker,
+}
+
+pub fn spawn(app: &AppHandle) {
+    let app = app.clone();
+    let mut stop_watch = app.state::<StopWatchState>().subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut run: Option<Run> = None;
+        let mut next_tick = Instant::now();
+        loop {
+            // The next tick is only waited for while it's running.
+            let changed = if run.is_some() {
+                timeout_at(next_tick, stop_watch.changed()).await.ok()
+            } else {
+                Some(stop_watch.changed().await)
+            };
+            match changed {
+                Some(Err(_)) => return,
+                Some(Ok(())) => {
+                    let start = stop_watch
+                        .borrow_and_update()
+                        .as_ref()
+                        .and_then(timer::running_since);
+                    run = follow(&app, run, start);
+                    next_tick = Instant::now();
+                }
+                None => {
+                    if let Some(run) = run.as_mut() {
+                        tick(&app, run);
+                    }
+                    // Once back from sleep, a single tick catches up.
+                    next_tick = (next_tick + INTERVAL).max(Instant::now());
+                }
+            }
+        }
+    });
+}
+
+pub fn is_running(app: &AppHandle) -> bool {
+    app.state::<Elapsed>().borrow().is_some()
+}
+
+fn follow(app: &AppHandle, run: Option<Run>, start: Option<NaiveDateTime>) -> Option<Run> {
+    let run = match (run, start) {
+        (Some(run), Some(start)) if run.start == start => return Some(run),
+        (_, Some(start)) => new_run(app, start),
+        (_, None) => None,
+    };
+    if run.is_none() {
+        app.state::<Elapsed>().send_replace(None);
+    }
+    run
+}
+
+fn new_run(app: &AppHandle, start: NaiveDateTime) -> Option<Run> {
+    let started = match utils::instant(start, timer::timezone(app)) {
+        Some(v) => v,
+        None => {
+            eprintln!(
+                "The start time {} doesn't exist in the time zone of the tenant",
+                start
+            );
+            return None;
+        }
+    };
+    app.state::<IdleHandle>()
+        .lock()
+        .unwrap()
+        .set_running_since(start);
+    Some(Run {
+        start,
+        started,
+        alerts: AlertTracker::new(started.with_timezone(&Local).naive_local()),
+    })
+}
+
+fn tick(app: &AppHandle, run: &mut Run) {
+    let now = Utc::now();
+    let elapsed = (now - run.started).max(TimeDelta::zero());
+    app.state::<Elapsed>().send_replace(Some(elapsed));
+
+    let clock = elapsed.to_clock_str();
+    tray::set_elapsed(app, &clock);
+    app.emit_all("timer_tick", Some(clock))
+        .expect("failed to emit timer_tick event");
+
+    let settings = app.state::<Mutex<Settings>>();
+    let thresholds = settings.lock().unwrap().preferences.alerts.clone();
+    let local_now = now.with_timezone(&Local).naive_local();
+    if let Some(alert) = run.alerts.check(local_now, &thresholds) {
+        alert::notify(app, &alert);
+    }
+}
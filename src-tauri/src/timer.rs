@@ -0,0 +1,1043 @@
+// Stopwatch actions shared by the commands invoked from the UI and the system tray.
+
+use crate::idle::IdleHandle;
+use crate::offline::{self, Action, OfflineStore};
+use crate::rounding::Rounding;
+use crate::settings::Settings;
+use crate::tray;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use crowdlog::api::{
+    ApiClient, Process, Project, ReqError, StopWatch, StopWatchStatus, Timesheet, TimesheetInput,
+    WorkContent,
+};
+use crowdlog::utils::{self, DATE_FORMAT, TIME_FORMAT};
+use crowdlog::Tz;
+use std::future::Future;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+
+// The stopwatch as the app knows it, from the last response of Crowdlog or the actions queued while offline.
+// None until it has been fetched. The ticker follows it.
+pub type StopWatchState = watch::Sender<Option<StopWatch>>;
+
+pub trait ToClockStr {
+    fn to_clock_str(&self) -> String;
+}
+impl ToClockStr for TimeDelta {
+    fn to_clock_str(&self) -> String {
+        let h = self.num_hours();
+        let m = self.num_minutes() % 60;
+        let s = self.num_seconds() % 60;
+        format!("{:02}:{:02}:{:02}", h, m, s)
+    }
+}
+
+pub async fn start(app: &AppHandle, stop_watch: StopWatch) -> Result<StopWatch, ReqError> {
+    let api = app.state::<ApiClient>();
+    let sw =
+        match stop_watch.status {
+            StopWatchStatus::Started => stop_watch,
+            StopWatchStatus::Clean => {
+                match unless_offline(app, api.start_timer(stop_watch.id)).await? {
+                    Some(sw) => sw,
+                    None => {
+                        queue(app, Action::Start)?;
+                        StopWatch {
+                            status: StopWatchStatus::Started,
+                            start_at: utils::now(timezone(app)),
+                            stop_at: None,
+                            ..stop_watch
+                        }
+                    }
+                }
+            }
+            StopWatchStatus::NeedToApply => return Err(ReqError {
+                status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+                message: String::from(
+                    "Timer is stopped without applying a work content. Apply or discard it first.",
+                ),
+                source: None,
+            }),
+        };
+
+    update_state(app, &sw);
+    Ok(sw)
+}
+
+pub async fn stop(app: &AppHandle, stop_watch: StopWatch) -> Result<StopWatch, ReqError> {
+    stop_at(app, stop_watch, None).await
+}
+
+// Records the time until `at` instead of until now, e.g. when the user has been idle since then.
+// Either way, the idle time the user chose to subtract before is left out.
+pub async fn stop_at(
+    app: &AppHandle,
+    stop_watch: StopWatch,
+    at: Option<NaiveDateTime>,
+) -> Result<StopWatch, ReqError> {
+    let api = app.state::<ApiClient>();
+    let rounding = rounding(app);
+    // Only taken once recorded or queued, so that it's still left out when tried again.
+    let subtracted = app.state::<IdleHandle>().lock().unwrap().subtracted();
+
+    let req = async {
+        let stopped = api.stop_timer(stop_watch.id).await?;
+        let end_at = recorded_end_at(&stopped, at, subtracted, &rounding, timezone(app));
+        apply(&api, &stopped, end_at.as_deref()).await?;
+        api.reset_timer(stop_watch.id).await
+    };
+    let sw = match unless_offline(app, req).await? {
+        Some(sw) => sw,
+        None => {
+            let stopped_at = at.unwrap_or_else(|| tenant_now(app)) - subtracted;
+            queue_at(
+                app,
+                Action::Stop,
+                &stopped_at.format(TIME_FORMAT).to_string(),
+            )?;
+            cleaned(stop_watch)
+        }
+    };
+    app.state::<IdleHandle>().lock().unwrap().take_subtracted();
+
+    update_state(app, &sw);
+    Ok(sw)
+}
+
+// Records the running entry and starts the next one in a single action.
+// The entry ends right when the next one starts, see `switch_stopped`.
+pub async fn switch(
+    app: &AppHandle,
+    stop_watch: StopWatch,
+    project_id: u32,
+    process_id: u32,
+    sub_process_ids: &[u32],
+) -> Result<StopWatch, ReqError> {
+    if !matches!(stop_watch.status, StopWatchStatus::Started) {
+        return Err(ReqError {
+            status: reqwest::StatusCode::BAD_REQUEST.as_u16(),
+            message: String::from(
+                "Timer is not running. Set the work content and start it instead.",
+            ),
+            source: None,
+        });
+    }
+    let api = app.state::<ApiClient>();
+    let idle = app.state::<IdleHandle>();
+    let rounding = rounding(app);
+    let mut process_ids = vec![process_id];
+    process_ids.extend(sub_process_ids);
+
+    // Nothing has changed on the server until the stopwatch is stopped.
+    let stopped = match unless_offline(app, api.stop_timer(stop_watch.id)).await? {
+        Some(sw) => sw,
+        None => {
+            // Queued at the same time, so that the stop is recorded until the start.
+            let at = tenant_now(app);
+            let subtracted = idle.lock().unwrap().subtracted();
+            let stopped_at = (at - subtracted).format(TIME_FORMAT).to_string();
+            let at = at.format(TIME_FORMAT).to_string();
+            queue_at(app, Action::Stop, &stopped_at)?;
+            queue_at(
+                app,
+                Action::SetWorkContent {
+                    project_id,
+                    process_id,
+                    sub_process_ids: sub_process_ids.to_vec(),
+                },
+                &at,
+            )?;
+            queue_at(app, Action::Start, &at)?;
+            // Taken once queued, as when stopping.
+            idle.lock().unwrap().take_subtracted();
+
+            let sw = StopWatch {
+                start_at: at,
+                work_content: cached_work_content(app, project_id, &process_ids),
+                ..stop_watch
+            };
+            update_state(app, &sw);
+            tray::refresh_menu(app);
+            return Ok(sw);
+        }
+    };
+
+    let req = switch_stopped(
+        &api,
+        &idle,
+        &stopped,
+        project_id,
+        &process_ids,
+        &rounding,
+        timezone(app),
+    );
+    let sw = match req.await {
+        Ok(sw) => sw,
+        Err(e) => {
+            // Rolled back, or left as far as it went.
+            if let Ok(sw) = api.get_stop_watch().await {
+                update_state(app, &sw);
+            }
+            return Err(e);
+        }
+    };
+
+    update_state(app, &sw);
+    tray::refresh_menu(app);
+    Ok(sw)
+}
+
+// Records the time of the `stopped` stopwatch and starts it again on the next work content.
+// The time is recorded before anything else changes, so that it can't be lost by a step failing afterwards,
+// and the idle time to subtract is taken then.
+// Once started, the entry is moved to end at the start of the next one: the boundary is the same instant on both sides,
+// and rounding and idle time move the start of the entry instead.
+pub async fn switch_stopped(
+    api: &ApiClient,
+    idle: &IdleHandle,
+    stopped: &StopWatch,
+    project_id: u32,
+    process_ids: &[u32],
+    rounding: &Rounding,
+    tz: Tz,
+) -> Result<StopWatch, ReqError> {
+    let subtracted = idle.lock().unwrap().subtracted();
+    // Recorded as when stopped if the switch is rolled back.
+    let end_at = recorded_end_at(stopped, None, subtracted, rounding, tz);
+    if let Err(e) = api.apply_timer(stopped.id).await {
+        roll_back_switch(api, stopped, end_at.as_deref(), false, tz).await;
+        return Err(e);
+    }
+    idle.lock().unwrap().take_subtracted();
+
+    let req = async {
+        api.reset_timer(stopped.id).await?;
+        api.add_work_content_to_history(project_id, process_ids)
+            .await?;
+        // The next entry starts without the memo of the previous one.
+        api.set_work_content(stopped.id, project_id, process_ids, "")
+            .await?;
+        api.start_timer(stopped.id).await
+    };
+    let started = match req.await {
+        Ok(sw) => sw,
+        Err(e) => {
+            roll_back_switch(api, stopped, end_at.as_deref(), true, tz).await;
+            return Err(e);
+        }
+    };
+
+    if let Some(boundary) = parse_time(&started.start_at) {
+        let start = switched_start(stopped, boundary, subtracted, rounding);
+        retime_applied(api, stopped, start, boundary).await;
+    }
+    Ok(started)
+}
+
+// Start of a switched entry ending at `end`, so that it lasts as long as it would be recorded if stopped then.
+fn switched_start(
+    stopped: &StopWatch,
+    end: NaiveDateTime,
+    subtracted: TimeDelta,
+    rounding: &Rounding,
+) -> Option<NaiveDateTime> {
+    let start = parse_time(&stopped.start_at)?;
+    let worked = (end.signed_duration_since(start) - subtracted).max(TimeDelta::zero());
+    Some(end - rounding.apply(worked))
+}
+
+// Timesheet entry of a stopwatch switched to another work content, ending at `end`.
+// None if it would end before it has started, or if it has no work content.
+fn switched_entry(stopped: &StopWatch, end: NaiveDateTime) -> Option<TimesheetInput> {
+    let start = parse_time(&stopped.start_at)?;
+    let (project_id, process_id) = stopped.work_content.ids()?;
+    if end < start {
+        return None;
+    }
+    Some(TimesheetInput {
+        date: start.format(DATE_FORMAT).to_string(),
+        project_id,
+        process_id,
+        sub_process_ids: stopped
+            .work_content
+            .process_ids()
+            .into_iter()
+            .skip(1)
+            .collect(),
+        start_at: Some(stopped.start_at.clone()),
+        end_at: Some(end.format(TIME_FORMAT).to_string()),
+        duration: u32::try_from(end.signed_duration_since(start).num_minutes()).ok()?,
+        memo: stopped.work_content.memo().to_string(),
+    })
+}
+
+// Crowdlog can't resume a stopwatch, so a failed switch ends as if the stopwatch was stopped instead:
+// the time is recorded until `end_at`, and the previous work content is back on the clean stopwatch.
+// Errors are only logged, the switch has failed either way.
+async fn roll_back_switch(
+    api: &ApiClient,
+    stopped: &StopWatch,
+    end_at: Option<&str>,
+    is_recorded: bool,
+    tz: Tz,
+) {
+    let res = async {
+        let sw = api.get_stop_watch().await?;
+        match (&sw.status, is_recorded) {
+            (StopWatchStatus::NeedToApply, false) => {
+                apply(api, &sw, end_at).await?;
+                api.reset_timer(sw.id).await?;
+            }
+            // Changed elsewhere meanwhile, it can only be recorded as a new entry.
+            (_, false) => {
+                let end = match end_at {
+                    Some(v) => parse_time(v),
+                    None => stop_time(stopped, tz),
+                };
+                let entry = end
+                    .and_then(|v| switched_entry(stopped, v))
+                    .ok_or_else(|| ReqError {
+                        status: 500,
+                        message: format!("Unable to record the time from {}", stopped.start_at),
+                        source: None,
+                    })?;
+                api.create_timesheet(&entry).await?;
+            }
+            // Recorded until the stop time by the switch.
+            (status, true) => {
+                if let Some(end) = end_at.and_then(parse_time) {
+                    retime_applied(api, stopped, None, end).await;
+                }
+                if !matches!(status, StopWatchStatus::Clean) {
+                    api.reset_timer(sw.id).await?;
+                }
+            }
+        }
+
+        let project_id = stopped.work_content.ids().map_or(0, |(id, _)| id);
+        let process_ids = stopped.work_content.process_ids();
+        let memo = stopped.work_content.memo();
+        api.set_work_content(sw.id, project_id, &process_ids, memo)
+            .await
+    };
+    if let Err(e) = res.await {
+        eprintln!("Unable to roll back the switch: {:#?}", e);
+    }
+}
+
+// The stopwatch the commands act on, fetched if it's not known yet.
+pub async fn current(app: &AppHandle) -> Result<StopWatch, ReqError> {
+    if let Some(sw) = app.state::<StopWatchState>().borrow().clone() {
+        return Ok(sw);
+    }
+    let sw = app.state::<ApiClient>().get_stop_watch().await?;
+    update_state(app, &sw);
+    Ok(sw)
+}
+
+// Keeps the stopwatch as the app knows it, shows it in the tray,
+// and pushes it to every window as a `stop_watch_changed` event when it has changed.
+pub fn update_state(app: &AppHandle, sw: &StopWatch) {
+    let has_changed = app
+        .state::<StopWatchState>()
+        .send_replace(Some(sw.clone()))
+        .map_or(true, |v| is_changed(&v, sw));
+    tray::update(app, sw);
+    if has_changed {
+        app.emit_all("stop_watch_changed", sw)
+            .expect("failed to emit stop_watch_changed event");
+    }
+}
+
+// Whether it has been started, stopped, reset, or given another work content or memo.
+pub fn is_changed(last: &StopWatch, current: &StopWatch) -> bool {
+    let key = |sw: &StopWatch| {
+        let wc = &sw.work_content;
+        (
+            sw.status.clone(),
+            sw.start_at.clone(),
+            sw.stop_at.clone(),
+            wc.ids().map(|(project_id, _)| project_id),
+            wc.process_ids(),
+            wc.memo().to_string(),
+        )
+    };
+    key(last) != key(current)
+}
+
+// Applies a stopwatch that has been stopped without applying, e.g. from their web app.
+pub async fn apply_pending(app: &AppHandle, stop_watch: StopWatch) -> Result<StopWatch, ReqError> {
+    let api = app.state::<ApiClient>();
+    let rounding = rounding(app);
+
+    let end_at = recorded_end_at(
+        &stop_watch,
+        None,
+        TimeDelta::zero(),
+        &rounding,
+        timezone(app),
+    );
+    apply(&api, &stop_watch, end_at.as_deref()).await?;
+    let sw = api.reset_timer(stop_watch.id).await?;
+
+    update_state(app, &sw);
+    Ok(sw)
+}
+
+pub async fn cancel(app: &AppHandle, stop_watch: StopWatch) -> Result<StopWatch, ReqError> {
+    let api = app.state::<ApiClient>();
+    let sw = match unless_offline(app, api.reset_timer(stop_watch.id)).await? {
+        Some(sw) => sw,
+        None => {
+            queue(app, Action::Cancel)?;
+            cleaned(stop_watch)
+        }
+    };
+
+    update_state(app, &sw);
+    Ok(sw)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct StopPreview {
+    elapsed: String,
+    // Idle time left out, if any.
+    subtracted: Option<String>,
+    recorded: String,
+    rounding: Rounding,
+}
+
+// What would be recorded if the stopwatch was applied now, to confirm it beforehand.
+// A running stopwatch is counted as if it was stopped now.
+pub fn preview_stop(app: &AppHandle, stop_watch: &StopWatch) -> Option<StopPreview> {
+    let rounding = rounding(app);
+    let start = parse_time(&stop_watch.start_at)?;
+    let elapsed = stop_time(stop_watch, timezone(app))?.signed_duration_since(start);
+    let subtracted = match stop_watch.status {
+        StopWatchStatus::Started => app.state::<IdleHandle>().lock().unwrap().subtracted(),
+        _ => TimeDelta::zero(),
+    };
+    let recorded = (elapsed - subtracted).max(TimeDelta::zero());
+
+    Some(StopPreview {
+        elapsed: elapsed.to_clock_str(),
+        subtracted: (subtracted > TimeDelta::zero()).then(|| subtracted.to_clock_str()),
+        recorded: rounding.apply(recorded).to_clock_str(),
+        rounding,
+    })
+}
+
+// Time zone of the tenant, the one of every date and time given to and by Crowdlog.
+pub fn timezone(app: &AppHandle) -> Tz {
+    app.state::<ApiClient>().timezone()
+}
+
+pub fn tenant_now(app: &AppHandle) -> NaiveDateTime {
+    utils::tenant_time(Utc::now(), timezone(app))
+}
+
+pub fn rounding(app: &AppHandle) -> Rounding {
+    app.state::<Mutex<Settings>>()
+        .lock()
+        .unwrap()
+        .preferences
+        .rounding
+}
+
+// Applies the stopped stopwatch, then ends the recorded entry at `end_at` if given,
+// as Crowdlog records it until the stop time whatever is sent.
+pub async fn apply(
+    api: &ApiClient,
+    stopped: &StopWatch,
+    end_at: Option<&str>,
+) -> Result<(), ReqError> {
+    api.apply_timer(stopped.id).await?;
+    if let Some(end) = end_at.and_then(parse_time) {
+        retime_applied(api, stopped, None, end).await;
+    }
+    Ok(())
+}
+
+// Moves the entry recorded by applying `stopped` to end at `end`, and to start at `start` if given.
+// Once applied the time is recorded, so the entry is only left as it is if it can't be edited:
+// failing would let the stopwatch be applied twice.
+async fn retime_applied(
+    api: &ApiClient,
+    stopped: &StopWatch,
+    start: Option<NaiveDateTime>,
+    end: NaiveDateTime,
+) {
+    let req = async {
+        let entry = api.find_applied_timesheet(stopped).await?;
+        let input = entry.as_ref().and_then(|v| {
+            let start = start.or_else(|| parse_time(v.start_at.as_deref()?))?;
+            retimed(v, start, end)
+        });
+        match (entry, input) {
+            (Some(entry), Some(input)) => api.edit_timesheet(entry.id, &input).await,
+            _ => Err(ReqError {
+                status: 500,
+                message: format!(
+                    "Unable to end the entry from {} at {}",
+                    stopped.start_at, end
+                ),
+                source: None,
+            }),
+        }
+    };
+    if let Err(e) = req.await {
+        eprintln!("Left the recorded entry as it is: {:#?}", e);
+    }
+}
+
+// The entry moved to run from `start` to `end`, keeping its work content and memo.
+// None if it would end before it has started, or if it has no work content.
+fn retimed(entry: &Timesheet, start: NaiveDateTime, end: NaiveDateTime) -> Option<TimesheetInput> {
+    if end < start {
+        return None;
+    }
+    Some(TimesheetInput {
+        date: start.format(DATE_FORMAT).to_string(),
+        start_at: Some(start.format(TIME_FORMAT).to_string()),
+        end_at: Some(end.format(TIME_FORMAT).to_string()),
+        duration: u32::try_from(end.signed_duration_since(start).num_minutes()).ok()?,
+        ..entry.to_input()?
+    })
+}
+
+// End of the recorded time after rounding, or None to let Crowdlog use the stop time as is.
+// It ends at `at` if given, otherwise at the stop time, minus the `subtracted` idle time.
+pub fn recorded_end_at(
+    stopped: &StopWatch,
+    at: Option<NaiveDateTime>,
+    subtracted: TimeDelta,
+    rounding: &Rounding,
+    tz: Tz,
+) -> Option<String> {
+    let stop = match at {
+        Some(v) => v - subtracted,
+        None if !rounding.is_enabled() && subtracted.is_zero() => return None,
+        None => stop_time(stopped, tz)? - subtracted,
+    };
+    end_at(&stopped.start_at, stop, rounding)
+}
+
+// End of the recorded time of a stopwatch stopped at `stopped_at` while offline, after rounding.
+pub fn replayed_end_at(sw: &StopWatch, stopped_at: &str, rounding: &Rounding) -> Option<String> {
+    end_at(&sw.start_at, parse_time(stopped_at)?, rounding)
+}
+
+// Timesheet entry of a stopwatch started at `started_at` and stopped at `stopped_at` while offline, after rounding.
+pub fn replayed_entry(
+    sw: &StopWatch,
+    started_at: &str,
+    stopped_at: &str,
+    rounding: &Rounding,
+) -> Option<TimesheetInput> {
+    let sw = StopWatch {
+        start_at: started_at.to_string(),
+        ..sw.clone()
+    };
+    let end = parse_time(&replayed_end_at(&sw, stopped_at, rounding)?)?;
+    switched_entry(&sw, end)
+}
+
+fn end_at(start_at: &str, stop: NaiveDateTime, rounding: &Rounding) -> Option<String> {
+    let start = parse_time(start_at)?;
+    if stop < start {
+        return None;
+    }
+    let recorded = rounding.apply(stop.signed_duration_since(start));
+    Some((start + recorded).format(TIME_FORMAT).to_string())
+}
+
+// When the running stopwatch has been started, None if it's not running or its start can't be read.
+pub fn running_since(sw: &StopWatch) -> Option<NaiveDateTime> {
+    match sw.status {
+        StopWatchStatus::Started => parse_time(&sw.start_at),
+        _ => None,
+    }
+}
+
+// Time the stopwatch has been running at `now`, wherever this machine is.
+// Never negative when the clocks disagree.
+pub fn elapsed(sw: &StopWatch, now: DateTime<Utc>, tz: Tz) -> Option<TimeDelta> {
+    let start = utils::instant(running_since(sw)?, tz)?;
+    Some((now - start).max(TimeDelta::zero()))
+}
+
+fn stop_time(sw: &StopWatch, tz: Tz) -> Option<NaiveDateTime> {
+    match sw.stop_at.as_deref() {
+        Some(v) => parse_time(v),
+        None => Some(utils::tenant_time(Utc::now(), tz)),
+    }
+}
+
+pub fn parse_time(str: &str) -> Option<NaiveDateTime> {
+    match NaiveDateTime::parse_from_str(str, TIME_FORMAT) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            eprintln!("Unable to parse {:?}: {}", str, e);
+            None
+        }
+    }
+}
+
+// Also adds the work content to the history, as their web app does.
+// `sub_process_ids` are the processes of level 2 and 3 under `process_id`, if any.
+pub async fn set_work_content(
+    app: &AppHandle,
+    stop_watch: &StopWatch,
+    project_id: u32,
+    process_id: u32,
+    sub_process_ids: &[u32],
+) -> Result<WorkContent, ReqError> {
+    let api = app.state::<ApiClient>();
+    let mut process_ids = vec![process_id];
+    process_ids.extend(sub_process_ids);
+
+    let req = async {
+        api.add_work_content_to_history(project_id, &process_ids)
+            .await?;
+        let memo = stop_watch.work_content.memo();
+        api.set_work_content(stop_watch.id, project_id, &process_ids, memo)
+            .await
+    };
+    let wc = match unless_offline(app, req).await? {
+        Some(wc) => wc,
+        None => {
+            queue(
+                app,
+                Action::SetWorkContent {
+                    project_id,
+                    process_id,
+                    sub_process_ids: sub_process_ids.to_vec(),
+                },
+            )?;
+            cached_work_content(app, project_id, &process_ids)
+                .with_memo(stop_watch.work_content.memo())
+        }
+    };
+
+    let mut sw = stop_watch.clone();
+    sw.work_content = wc.clone();
+    update_state(app, &sw);
+    tray::refresh_menu(app);
+    Ok(wc)
+}
+
+// Keeps the project and the processes of the stopwatch.
+pub async fn set_memo(
+    app: &AppHandle,
+    stop_watch: &StopWatch,
+    memo: &str,
+) -> Result<WorkContent, ReqError> {
+    let api = app.state::<ApiClient>();
+    let project_id = stop_watch.work_content.ids().map_or(0, |(id, _)| id);
+    let process_ids = stop_watch.work_content.process_ids();
+    let req = api.set_work_content(stop_watch.id, project_id, &process_ids, memo);
+    let wc = match unless_offline(app, req).await? {
+        Some(wc) => wc,
+        None => {
+            queue(
+                app,
+                Action::SetMemo {
+                    memo: memo.to_string(),
+                },
+            )?;
+            stop_watch.work_content.clone().with_memo(memo)
+        }
+    };
+
+    let mut sw = stop_watch.clone();
+    sw.work_content = wc.clone();
+    update_state(app, &sw);
+    Ok(wc)
+}
+
+// Runs the request unless older actions are waiting to be replayed, so that they are replayed in order.
+// None when the action has to be queued instead, because of that or because Crowdlog is unreachable.
+async fn unless_offline<T>(
+    app: &AppHandle,
+    req: impl Future<Output = Result<T, ReqError>>,
+) -> Result<Option<T>, ReqError> {
+    if app.state::<OfflineStore>().has_pending() {
+        return Ok(None);
+    }
+    match req.await {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if e.is_unreachable() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn queue(app: &AppHandle, action: Action) -> Result<(), ReqError> {
+    queue_at(app, action, &utils::now(timezone(app)))
+}
+
+fn queue_at(app: &AppHandle, action: Action, recorded_at: &str) -> Result<(), ReqError> {
+    app.state::<OfflineStore>()
+        .queue(&action, recorded_at)
+        .map_err(|e| ReqError {
+            status: 500,
+            message: String::from("Unable to record the action while offline"),
+            source: Some(e.to_string()),
+        })
+}
+
+fn cleaned(stop_watch: StopWatch) -> StopWatch {
+    StopWatch {
+        status: StopWatchStatus::Clean,
+        start_at: String::new(),
+        stop_at: None,
+        ..stop_watch
+    }
+}
+
+// Names are looked up from the last responses, as the server can't be asked.
+fn cached_work_content(app: &AppHandle, project_id: u32, process_ids: &[u32]) -> WorkContent {
+    let store = app.state::<OfflineStore>();
+    let project = store
+        .get_cache::<Vec<Project>>(offline::PROJECTS)
+        .and_then(|v| v.into_iter().find(|pj| pj.id == project_id))
+        .unwrap_or_else(|| Project {
+            id: project_id,
+            name: format!("#{}", project_id),
+        });
+
+    let mut processes = vec![];
+    let mut parent_id = None;
+    for (level, &process_id) in (1..).zip(process_ids.iter()) {
+        let process = store
+            .get_cache::<Vec<Process>>(&offline::processes(project_id, level, parent_id))
+            .and_then(|v| v.into_iter().find(|pc| pc.id == process_id))
+            .unwrap_or_else(|| Process {
+                id: process_id,
+                name: format!("#{}", process_id),
+            });
+        processes.push(process);
+        parent_id = Some(process_id);
+    }
+
+    let mut processes = processes.into_iter();
+    let process = processes.next().unwrap_or(Process {
+        id: 0,
+        name: String::new(),
+    });
+    WorkContent::new(project, process).with_sub_processes(processes.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idle::{IdlePeriod, IdleTracker};
+    use chrono::TimeZone;
+    use crowdlog::mock_server::{self, block_on, MockServer};
+    use crowdlog::Credentials;
+    use serde_json::json;
+
+    fn stop_watch(start_at: &str) -> StopWatch {
+        let project = Project {
+            id: 1,
+            name: String::from("Acme Corp"),
+        };
+        let process = |id: u32, name: &str| Process {
+            id,
+            name: String::from(name),
+        };
+        StopWatch {
+            id: 42,
+            start_at: String::from(start_at),
+            stop_at: None,
+            status: StopWatchStatus::NeedToApply,
+            work_content: WorkContent::new(project, process(12, "Development"))
+                .with_sub_processes(vec![process(121, "Backend")])
+                .with_memo("API"),
+        }
+    }
+
+    fn time(str: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(str, TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn switched_entry_ends_at_the_boundary() {
+        let sw = stop_watch("2024-07-01 09:00:00");
+        let entry = switched_entry(&sw, time("2024-07-01 10:29:59")).unwrap();
+        assert_eq!(entry.date, "2024-07-01");
+        assert_eq!((entry.project_id, entry.process_id), (1, 12));
+        assert_eq!(entry.sub_process_ids, vec![121]);
+        assert_eq!(entry.start_at.as_deref(), Some("2024-07-01 09:00:00"));
+        assert_eq!(entry.end_at.as_deref(), Some("2024-07-01 10:29:59"));
+        assert_eq!(entry.duration, 89);
+        assert_eq!(entry.memo, "API");
+
+        // Recorded on the day it has started
+        let sw = stop_watch("2024-07-01 23:30:00");
+        let entry = switched_entry(&sw, time("2024-07-02 00:15:00")).unwrap();
+        assert_eq!(entry.date, "2024-07-01");
+        assert_eq!(entry.duration, 45);
+
+        assert!(switched_entry(&sw, time("2024-07-01 23:29:59")).is_none());
+        let unset = StopWatch {
+            work_content: WorkContent::new(
+                Project {
+                    id: 0,
+                    name: String::new(),
+                },
+                Process {
+                    id: 0,
+                    name: String::new(),
+                },
+            ),
+            ..sw
+        };
+        assert!(switched_entry(&unset, time("2024-07-02 00:15:00")).is_none());
+    }
+
+    fn logged_in_client(server: &MockServer) -> ApiClient {
+        let api = ApiClient::new(server.base_url());
+        block_on(api.login(&Credentials {
+            email: String::from(mock_server::EMAIL),
+            passwd: String::from(mock_server::PASSWD),
+        }))
+        .unwrap();
+        api
+    }
+
+    // Stopped on the server after running for 95 minutes, 20 of them idle.
+    fn stopped_with_idle_time(server: &MockServer, api: &ApiClient) -> (StopWatch, IdleHandle) {
+        let tokyo = utils::DEFAULT_TIMEZONE;
+        let start = utils::tenant_time(Utc::now(), tokyo) - TimeDelta::minutes(95);
+        block_on(api.set_work_content(mock_server::STOP_WATCH_ID, 1, &[12, 121], "API")).unwrap();
+        {
+            let mut state = server.state();
+            state.stop_watch["status"] = json!("timing");
+            state.stop_watch["start_at"] = json!(start.format(TIME_FORMAT).to_string());
+        }
+        let stopped = block_on(api.stop_timer(mock_server::STOP_WATCH_ID)).unwrap();
+
+        let mut tracker = IdleTracker::default();
+        tracker.set_running_since(start);
+        let idle_start = start + TimeDelta::minutes(30);
+        tracker.subtract(&IdlePeriod {
+            start_at: idle_start.format(TIME_FORMAT).to_string(),
+            end_at: (idle_start + TimeDelta::minutes(20))
+                .format(TIME_FORMAT)
+                .to_string(),
+        });
+        (stopped, Mutex::new(tracker))
+    }
+
+    #[test]
+    fn switch_at_the_start_of_the_next_entry() {
+        let server = MockServer::start();
+        let api = logged_in_client(&server);
+        let (stopped, idle) = stopped_with_idle_time(&server, &api);
+        let rounding = Rounding {
+            mode: crate::rounding::RoundingMode::Nearest,
+            unit_minutes: 15,
+        };
+
+        let started = block_on(switch_stopped(
+            &api,
+            &idle,
+            &stopped,
+            2,
+            &[21],
+            &rounding,
+            utils::DEFAULT_TIMEZONE,
+        ))
+        .unwrap();
+        assert!(matches!(started.status, StopWatchStatus::Started));
+        assert_eq!(started.work_content.ids(), Some((2, 21)));
+        assert_eq!(started.work_content.memo(), "");
+        assert!(idle.lock().unwrap().subtracted().is_zero());
+
+        let state = server.state();
+        assert_eq!(state.timesheets.len(), 1);
+        let entry = &state.timesheets[0];
+        // 95 minutes, 20 of them idle, recorded until the next one starts
+        assert_eq!(entry["end_at"], json!(started.start_at));
+        let boundary = parse_time(&started.start_at).unwrap();
+        let start = (boundary - TimeDelta::minutes(75))
+            .format(TIME_FORMAT)
+            .to_string();
+        assert_eq!(entry["start_at"], json!(start));
+        assert_eq!(entry["duration"], 75);
+        assert_eq!(entry["work_content"]["process_2"]["id"], 121);
+        assert_eq!(entry["memo"], "API");
+    }
+
+    #[test]
+    fn roll_back_a_failed_switch_as_a_stop() {
+        let steps = [
+            ("POST", "apis/my/timesheets/stop_watch_apply"),
+            ("PUT", "apis/my/stop_watches/42/reset"),
+            ("PUT", "apis/my/stop_watches/42"),
+            ("PUT", "apis/my/stop_watches/42/start"),
+        ];
+        for (method, path) in steps {
+            let server = MockServer::start();
+            let api = logged_in_client(&server);
+            let (stopped, idle) = stopped_with_idle_time(&server, &api);
+            server.fail_once(method, path);
+            let step = format!("{} {}", method, path);
+
+            let res = block_on(switch_stopped(
+                &api,
+                &idle,
+                &stopped,
+                2,
+                &[21],
+                &Rounding::default(),
+                utils::DEFAULT_TIMEZONE,
+            ));
+            assert!(res.is_err(), "{}", step);
+            // Taken once recorded by the switch
+            let is_recorded = method != "POST";
+            assert_eq!(
+                idle.lock().unwrap().subtracted().is_zero(),
+                is_recorded,
+                "{}",
+                step
+            );
+
+            let state = server.state();
+            assert_eq!(state.stop_watch["status"], "reset", "{}", step);
+            let wc = &state.stop_watch["work_content"];
+            assert_eq!(wc["project"]["id"], 1, "{}", step);
+            assert_eq!(wc["process"]["id"], 12, "{}", step);
+            assert_eq!(wc["process_2"]["id"], 121, "{}", step);
+            assert_eq!(wc["memo"], "API", "{}", step);
+            // Recorded once, until the stop time minus the idle time
+            assert_eq!(state.timesheets.len(), 1, "{}", step);
+            let entry = &state.timesheets[0];
+            let end =
+                parse_time(stopped.stop_at.as_deref().unwrap()).unwrap() - TimeDelta::minutes(20);
+            assert_eq!(
+                entry["end_at"],
+                json!(end.format(TIME_FORMAT).to_string()),
+                "{}",
+                step
+            );
+            assert_eq!(entry["start_at"], json!(stopped.start_at), "{}", step);
+        }
+    }
+
+    #[test]
+    fn subtract_idle_time_before_stopping_at_idle_start() {
+        let tokyo = utils::DEFAULT_TIMEZONE;
+        let mut tracker = crate::idle::IdleTracker::default();
+        tracker.set_running_since(time("2024-07-01 09:00:00"));
+        tracker.subtract(&crate::idle::IdlePeriod {
+            start_at: String::from("2024-07-01 10:05:00"),
+            end_at: String::from("2024-07-01 10:50:00"),
+        });
+
+        // Idle again from 12:00, and stopped then
+        let sw = stop_watch("2024-07-01 09:00:00");
+        let at = Some(time("2024-07-01 12:00:00"));
+        let subtracted = tracker.take_subtracted();
+        assert_eq!(
+            recorded_end_at(&sw, at, subtracted, &Rounding::default(), tokyo).as_deref(),
+            Some("2024-07-01 11:15:00")
+        );
+        let rounding = Rounding {
+            mode: crate::rounding::RoundingMode::Up,
+            unit_minutes: 30,
+        };
+        assert_eq!(
+            recorded_end_at(&sw, at, subtracted, &rounding, tokyo).as_deref(),
+            Some("2024-07-01 11:30:00")
+        );
+    }
+
+    #[test]
+    fn elapsed_while_running() {
+        let tokyo = utils::DEFAULT_TIMEZONE;
+        let utc = |str: &str| time(str).and_utc();
+        let started = StopWatch {
+            status: StopWatchStatus::Started,
+            ..stop_watch("2024-07-01 09:00:00")
+        };
+        assert_eq!(
+            elapsed(&started, utc("2024-07-01 01:30:15"), tokyo),
+            Some(TimeDelta::seconds(5415))
+        );
+        // The clock of this machine is behind the one of Crowdlog
+        assert_eq!(
+            elapsed(&started, utc("2024-06-30 23:59:58"), tokyo),
+            Some(TimeDelta::zero())
+        );
+
+        // Travelling in Paris
+        let paris: Tz = "Europe/Paris".parse().unwrap();
+        let now = paris.with_ymd_and_hms(2024, 7, 1, 3, 30, 0).unwrap();
+        assert_eq!(
+            elapsed(&started, now.with_timezone(&Utc), tokyo),
+            Some(TimeDelta::minutes(90))
+        );
+        // A tenant in New York
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        assert_eq!(
+            elapsed(&started, utc("2024-07-01 14:30:00"), new_york),
+            Some(TimeDelta::minutes(90))
+        );
+        // Over the midnight of the tenant
+        let late = StopWatch {
+            start_at: String::from("2024-07-01 23:30:00"),
+            ..started.clone()
+        };
+        assert_eq!(
+            elapsed(&late, utc("2024-07-01 15:15:00"), tokyo),
+            Some(TimeDelta::minutes(45))
+        );
+
+        let stopped = stop_watch("2024-07-01 09:00:00");
+        assert_eq!(elapsed(&stopped, utc("2024-07-01 01:30:15"), tokyo), None);
+        let unreadable = StopWatch {
+            start_at: String::from("2024/07/01 09:00"),
+            ..started
+        };
+        assert_eq!(
+            elapsed(&unreadable, utc("2024-07-01 01:30:15"), tokyo),
+            None
+        );
+    }
+
+    #[test]
+    fn detect_changes_made_elsewhere() {
+        let clean = StopWatch {
+            status: StopWatchStatus::Clean,
+            ..stop_watch("")
+        };
+        let started = StopWatch {
+            status: StopWatchStatus::Started,
+            ..stop_watch("2024-07-01 09:00:00")
+        };
+        assert!(!is_changed(&clean, &clean.clone()));
+        assert!(is_changed(&clean, &started));
+        assert!(is_changed(&started, &clean));
+
+        // Restarted in between two polls
+        let restarted = StopWatch {
+            start_at: String::from("2024-07-01 09:10:00"),
+            ..started.clone()
+        };
+        assert!(is_changed(&started, &restarted));
+
+        let switched = StopWatch {
+            work_content: started.work_content.clone().with_sub_processes(vec![]),
+            ..started.clone()
+        };
+        assert!(is_changed(&started, &switched));
+
+        let with_memo = StopWatch {
+            work_content: started.work_content.clone().with_memo("Wireframes"),
+            ..started.clone()
+        };
+        assert!(is_changed(&started, &with_memo));
+    }
+}
@@ -0,0 +1,246 @@
+// System tray icon to control the stopwatch without opening the window.
+
+use crate::offline::{self, OfflineStore};
+use crate::search::SearchHandle;
+use crate::timer::{self, StopWatchState};
+use crowdlog::api::{
+    ApiClient, Process, Project, ReqError, StopWatch, StopWatchStatus, WorkContent,
+};
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    SystemTrayMenuItem, SystemTraySubmenu,
+};
+
+const ELAPSED: &str = "elapsed";
+const WORK_CONTENT: &str = "work_content";
+pub const START: &str = "start";
+pub const STOP: &str = "stop";
+pub const CANCEL: &str = "cancel";
+const SHOW: &str = "show";
+const QUIT: &str = "quit";
+
+// Prefixes of the work content items, followed by `:{project_id}:{process_id}` and `:{process_id}` of each deeper level.
+// The same work content can appear in several submenus, and item ids must be unique.
+const RECENT: &str = "recent";
+const STARRED: &str = "starred";
+const PROJECT: &str = "project";
+
+const IDLE_CLOCK: &str = "--:--:--";
+
+pub fn build() -> SystemTray {
+    SystemTray::new().with_menu(menu(None))
+}
+
+// Work content submenus are only available once they have been fetched by `refresh_menu`.
+fn menu(work_contents: Option<WorkContentMenus>) -> SystemTrayMenu {
+    let mut menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(ELAPSED, IDLE_CLOCK).disabled())
+        .add_item(CustomMenuItem::new(WORK_CONTENT, "-").disabled())
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(START, "Start").disabled())
+        .add_item(CustomMenuItem::new(STOP, "Stop").disabled())
+        .add_item(CustomMenuItem::new(CANCEL, "Cancel").disabled())
+        .add_native_item(SystemTrayMenuItem::Separator);
+
+    if let Some(wc) = work_contents {
+        menu = menu
+            .add_submenu(SystemTraySubmenu::new("Recent", wc.recent))
+            .add_submenu(SystemTraySubmenu::new("Starred", wc.starred))
+            .add_submenu(SystemTraySubmenu::new("Projects", wc.projects))
+            .add_native_item(SystemTrayMenuItem::Separator);
+    }
+
+    menu.add_item(CustomMenuItem::new(SHOW, "Open CrowdDog"))
+        .add_item(CustomMenuItem::new(QUIT, "Quit"))
+}
+
+pub fn on_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            START | STOP | CANCEL => {
+                let app = app.clone();
+                let action = id.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = run_timer_action(&app, &action).await {
+                        eprintln!("{:#?}", e);
+                    }
+                });
+            }
+            SHOW => show_window(app),
+            QUIT => app.exit(0),
+            _ => {
+                if let Some((project_id, process_ids)) = parse_work_content_id(&id) {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = switch_work_content(&app, project_id, &process_ids).await {
+                            eprintln!("{:#?}", e);
+                        }
+                    });
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+// Reflects the status and work content of the stopwatch in the menu.
+pub fn update(app: &AppHandle, sw: &StopWatch) {
+    let tray = app.tray_handle();
+    let is_started = matches!(sw.status, StopWatchStatus::Started);
+    let is_clean = matches!(sw.status, StopWatchStatus::Clean);
+
+    let _ = tray
+        .get_item(WORK_CONTENT)
+        .set_title(sw.work_content.label());
+    let _ = tray.get_item(START).set_enabled(is_clean);
+    let _ = tray.get_item(STOP).set_enabled(is_started);
+    let _ = tray.get_item(CANCEL).set_enabled(is_started);
+    if !is_started {
+        set_elapsed(app, IDLE_CLOCK);
+    }
+}
+
+pub fn set_elapsed(app: &AppHandle, clock: &str) {
+    let _ = app.tray_handle().get_item(ELAPSED).set_title(clock);
+}
+
+// Rebuilds the work content submenus in the background, e.g. after the history has changed.
+// Only the history is fetched again, the starred work contents and the projects are the ones known already.
+pub fn refresh_menu(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = rebuild_menu(&app).await {
+            eprintln!("{:#?}", e);
+        }
+    });
+}
+
+struct WorkContentMenus {
+    recent: SystemTrayMenu,
+    starred: SystemTrayMenu,
+    projects: SystemTrayMenu,
+}
+
+async fn rebuild_menu(app: &AppHandle) -> Result<(), ReqError> {
+    let api = app.state::<ApiClient>();
+    let store = app.state::<OfflineStore>();
+    let history = api.get_history().await?;
+    let my_patterns = match store.get_cache::<Vec<WorkContent>>(offline::MY_PATTERNS) {
+        Some(v) => v,
+        None => {
+            let my_patterns = api.get_my_patterns().await?;
+            store.put_cache(offline::MY_PATTERNS, &my_patterns);
+            my_patterns
+        }
+    };
+    // Empty until the search index is built, which refreshes the menu again.
+    let mut projects = app.state::<SearchHandle>().lock().unwrap().projects();
+
+    // Recently used projects first, the rest keeps the server's order.
+    projects.sort_by_key(|(pj, _)| {
+        history
+            .iter()
+            .position(|wc| wc.ids().map(|(id, _)| id) == Some(pj.id))
+            .unwrap_or(usize::MAX)
+    });
+
+    let mut projects_menu = SystemTrayMenu::new();
+    for (pj, processes) in projects.iter() {
+        projects_menu = projects_menu.add_submenu(project_submenu(pj, processes));
+    }
+
+    let work_contents = WorkContentMenus {
+        recent: work_content_menu(RECENT, &history),
+        starred: work_content_menu(STARRED, &my_patterns),
+        projects: projects_menu,
+    };
+    let _ = app.tray_handle().set_menu(menu(Some(work_contents)));
+
+    // The new menu starts from the default state.
+    let sw = app.state::<StopWatchState>().borrow().clone();
+    if let Some(sw) = sw {
+        update(app, &sw);
+    }
+    Ok(())
+}
+
+fn work_content_menu(prefix: &str, list: &[WorkContent]) -> SystemTrayMenu {
+    let mut menu = SystemTrayMenu::new();
+    for wc in list.iter() {
+        if let Some((project_id, _)) = wc.ids() {
+            let id = work_content_id(prefix, project_id, &wc.process_ids());
+            menu = menu.add_item(CustomMenuItem::new(id, wc.label()));
+        }
+    }
+    menu
+}
+
+fn project_submenu(pj: &Project, processes: &[Process]) -> SystemTraySubmenu {
+    let mut menu = SystemTrayMenu::new();
+    for pc in processes.iter() {
+        let id = work_content_id(PROJECT, pj.id, &[pc.id]);
+        menu = menu.add_item(CustomMenuItem::new(id, pc.name.clone()));
+    }
+    SystemTraySubmenu::new(pj.name.clone(), menu)
+}
+
+fn work_content_id(prefix: &str, project_id: u32, process_ids: &[u32]) -> String {
+    let mut id = format!("{}:{}", prefix, project_id);
+    for process_id in process_ids.iter() {
+        id.push_str(&format!(":{}", process_id));
+    }
+    id
+}
+
+// The project id and the process ids from level 1.
+fn parse_work_content_id(id: &str) -> Option<(u32, Vec<u32>)> {
+    let mut parts = id.split(':');
+    let prefix = parts.next()?;
+    if ![RECENT, STARRED, PROJECT].contains(&prefix) {
+        return None;
+    }
+    let project_id = parts.next()?.parse().ok()?;
+    let process_ids = parts
+        .map(|v| v.parse().ok())
+        .collect::<Option<Vec<u32>>>()
+        .filter(|v| !v.is_empty())?;
+    Some((project_id, process_ids))
+}
+
+// Also used by the global shortcuts, which can be pressed whatever the status is.
+pub async fn run_timer_action(app: &AppHandle, id: &str) -> Result<(), ReqError> {
+    // From the stopwatch as the app knows it, which the polling keeps in line with the server.
+    let sw = timer::current(app).await?;
+    let is_applicable = match id {
+        START => matches!(sw.status, StopWatchStatus::Clean),
+        _ => matches!(sw.status, StopWatchStatus::Started),
+    };
+    if !is_applicable {
+        return Ok(());
+    }
+
+    match id {
+        START => timer::start(app, sw).await?,
+        STOP => timer::stop(app, sw).await?,
+        _ => timer::cancel(app, sw).await?,
+    };
+    Ok(())
+}
+
+async fn switch_work_content(
+    app: &AppHandle,
+    project_id: u32,
+    process_ids: &[u32],
+) -> Result<(), ReqError> {
+    let sw = timer::current(app).await?;
+    timer::set_work_content(app, &sw, project_id, process_ids[0], &process_ids[1..]).await?;
+    Ok(())
+}
+
+pub fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
@@ -28,9 +28,13 @@ type Process = {
 type WorkContent = {
 	project?: Project;
 	process?: Process;
+	// Deeper levels of the process, when the tenant uses them
+	process_2?: Process | null;
+	process_3?: Process | null;
+	memo: string;
 };
 
-type DefinedWorkContent = Required<WorkContent>;
+type DefinedWorkContent = WorkContent & Required<Pick<WorkContent, 'project' | 'process'>>;
 
 type State = {
 	user: User;
@@ -38,3 +42,126 @@ type State = {
 	projects: Project[];
 	history: DefinedWorkContent[];
 };
+
+type RoundingMode = 'off' | 'up' | 'down' | 'nearest';
+type Rounding = {
+	mode: RoundingMode;
+	unit_minutes: number;
+};
+
+// Accelerators such as `CmdOrCtrl+Alt+S`, empty to disable.
+type Shortcuts = {
+	start: string;
+	stop: string;
+	cancel: string;
+	switcher: string;
+};
+
+type IdleDetection = {
+	enabled: boolean;
+	threshold_minutes: number;
+};
+
+type TimerAlerts = {
+	// 0 to disable.
+	running_over_minutes: number;
+	// `hh:mm`, empty to disable.
+	running_after: string;
+};
+type TimerAlert = { kind: 'running_over'; minutes: number } | { kind: 'running_after'; time: string };
+
+type WorkingHours = {
+	enabled: boolean;
+	// From Monday (0) to Sunday (6).
+	days: number[];
+	// `hh:mm`, the end is excluded.
+	start: string;
+	end: string;
+	reminder_minutes: number;
+};
+type StartReminder = {
+	minutes: number;
+	// Last used one, if any.
+	work_content: WorkContent | null;
+};
+
+type Preferences = {
+	rounding: Rounding;
+	shortcuts: Shortcuts;
+	idle: IdleDetection;
+	alerts: TimerAlerts;
+	working_hours: WorkingHours;
+};
+
+type StopPreview = {
+	elapsed: string;
+	// Idle time left out, if any.
+	subtracted: string | null;
+	recorded: string;
+	rounding: Rounding;
+};
+
+// From the last input before going idle to the first one after.
+type IdlePeriod = {
+	start_at: string;
+	end_at: string;
+};
+type IdleChoice = 'keep' | 'subtract' | 'stop_at_idle_start';
+
+type Timesheet = {
+	id: number;
+	date: string;
+	start_at: string | null;
+	end_at: string | null;
+	// In minutes
+	duration: number;
+	memo: string;
+	work_content: WorkContent;
+};
+
+type TimesheetInput = {
+	date: string;
+	project_id: number;
+	process_id: number;
+	// Processes of level 2 and 3, if any
+	sub_process_ids?: number[];
+	start_at: string | null;
+	end_at: string | null;
+	duration: number;
+	memo: string;
+};
+
+type SearchResult = {
+	project: Project;
+	process: Process;
+	score: number;
+};
+
+type ProcessesPage = {
+	project_id: number;
+	level: number;
+	parent_id: number | null;
+	processes: Process[];
+	is_last: boolean;
+};
+
+type SummaryPeriod = 'day' | 'week';
+// Totals are in minutes
+type Summary = {
+	start_date: string;
+	end_date: string;
+	total: number;
+	days: Array<{ date: string; total: number }>;
+	projects: Array<{
+		id: number;
+		name: string;
+		total: number;
+		processes: Array<{ id: number; name: string; total: number }>;
+	}>;
+};
+
+type SyncConflict = {
+	action: 'start' | 'stop' | 'cancel' | 'set_work_content' | 'set_memo';
+	recorded_at: string;
+	reason: string;
+};
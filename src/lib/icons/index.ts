@@ -8,5 +8,20 @@ import Search from './Search.svelte';
 import Star from './Star.svelte';
 import List from './List.svelte';
 import History from './History.svelte';
+import Settings from './Settings.svelte';
+import Chart from './Chart.svelte';
 
-export { Eye, ExternalLink, Loader, Logout, Cancel, ChevronRight, Search, Star, List, History };
+export {
+	Eye,
+	ExternalLink,
+	Loader,
+	Logout,
+	Cancel,
+	ChevronRight,
+	Search,
+	Star,
+	List,
+	History,
+	Settings,
+	Chart,
+};
@@ -1,22 +1,24 @@
 import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
-import { store, TIMER_STATUS } from './store.svelte';
+import { store, TIMER_STATUS, is_defined_work_content } from './store.svelte';
 
 const EVENT = {
 	TIMER_TICK: 'timer_tick',
+	STOP_WATCH_CHANGED: 'stop_watch_changed',
+	SYNC_CONFLICTS: 'sync_conflicts',
+	PROCESSES_PAGE: 'processes_page',
+	OPEN_SWITCHER: 'open_switcher',
+	IDLE_DETECTED: 'idle_detected',
+	TIMER_ALERT: 'timer_alert',
+	START_REMINDER: 'start_reminder',
 };
 
-// Wrapper of invoke. It automatically re-logins and retry the command when the original command failed because of session expiration.
+// Wrapper of invoke. Session expiration is handled on the Rust side, so it only logs the error.
 const command = async <T>(...args: Parameters<typeof invoke>) => {
 	try {
 		const res = await invoke<T>(...args);
 		return res;
 	} catch (e) {
-		const err = e as App.Error;
-		if (err.status === 401) {
-			await invoke('re_login');
-			return invoke<T>(...args);
-		}
 		console.error(e);
 		throw e;
 	}
@@ -29,7 +31,7 @@ export const login = (credentials: Credentials) => {
 export const init_data = async () => {
 	const res = await command<State>('init_data');
 	if (res.stop_watch.status === TIMER_STATUS.STARTED) {
-		start_timer(res.stop_watch);
+		start_timer();
 	}
 	return {
 		...res,
@@ -41,6 +43,11 @@ export const clear_data = () => {
 	return command('clear_data');
 };
 
+// The stopwatch as the Rust side knows it, which may be newer than the one in the store.
+export const get_state = async () => {
+	return deserialize_sw(await command<StopWatch>('get_state'));
+};
+
 let my_patterns: Array<DefinedWorkContent> | undefined;
 export const get_my_patterns = async () => {
 	if (!my_patterns) {
@@ -50,55 +57,234 @@ export const get_my_patterns = async () => {
 	return my_patterns;
 };
 
-export const get_processes = (project_id: DefinedWorkContent['project']['id']) => {
-	return command<Process[]>('get_processes', { user_id: store.state.user.id, project_id });
+// Level 1 processes by default, or the ones under `parent_id` at a deeper `level`.
+export const get_processes = (
+	project_id: DefinedWorkContent['project']['id'],
+	level?: number,
+	parent_id?: Process['id'],
+) => {
+	return command<Process[]>('get_processes', {
+		user_id: store.state.user.id,
+		project_id,
+		level,
+		parent_id,
+	});
+};
+
+// Same as `get_processes`, but calls `on_page` as soon as every page is received.
+// Resolves after the last page.
+export const stream_processes = async (
+	project_id: DefinedWorkContent['project']['id'],
+	on_page: (processes: Process[]) => void,
+	level = 1,
+	parent_id: Process['id'] | null = null,
+) => {
+	let done = () => {};
+	const received_last = new Promise<void>((resolve) => (done = resolve));
+	const unlisten = await listen<ProcessesPage>(EVENT.PROCESSES_PAGE, (e) => {
+		const page = e.payload;
+		if (page.project_id !== project_id || page.level !== level || page.parent_id !== parent_id)
+			return;
+		on_page(page.processes);
+		if (page.is_last) done();
+	});
+
+	try {
+		await command<void>('stream_processes', {
+			user_id: store.state.user.id,
+			project_id,
+			level,
+			parent_id,
+		});
+		await received_last;
+	} finally {
+		unlisten();
+	}
+};
+
+// Pairs of a project and a process matching every word of the query, best matches first.
+export const search_work_contents = (query: string, limit?: number) => {
+	return command<SearchResult[]>('search_work_contents', { query, limit });
 };
 
 export const set_work_content = async (
 	project_id: DefinedWorkContent['project']['id'],
 	process_id: DefinedWorkContent['process']['id'],
+	sub_process_ids: Array<Process['id']> = [],
 ) => {
 	const wc = await command<WorkContent>('set_work_content', {
-		stop_watch: store.state.stop_watch,
 		project_id,
 		process_id,
+		sub_process_ids,
 	});
 	store.update_work_content(wc);
 };
 
+export const set_memo = async (memo: string) => {
+	const wc = await command<WorkContent>('set_memo', { memo });
+	store.update_memo(wc.memo);
+};
+
+export const get_preferences = () => {
+	return command<Preferences>('get_preferences');
+};
+
+export const set_preferences = (preferences: Preferences) => {
+	return command<void>('set_preferences', { preferences });
+};
+
+export const preview_stop = () => {
+	return command<StopPreview | null>('preview_stop');
+};
+
 let unlisten = () => {};
-export const start_timer = async (stop_watch: StopWatch) => {
-	const res = await command<StopWatch>('start_timer', { stop_watch });
-	const sw = deserialize_sw(res);
-	unlisten = await listen<string>(EVENT.TIMER_TICK, (e) => {
+// Started by both an action and its `stop_watch_changed` event, only one listener is kept.
+const listen_timer_tick = async () => {
+	unlisten();
+	const un = await listen<string>(EVENT.TIMER_TICK, (e) => {
 		store.setClock(e.payload);
 	});
+	unlisten();
+	unlisten = un;
+};
+
+export const start_timer = async () => {
+	const res = await command<StopWatch>('start_timer');
+	const sw = deserialize_sw(res);
+	await listen_timer_tick();
 	store.update_stop_watch(sw);
 	return sw;
 };
 
-export const stop_timer = async (stop_watch: StopWatch) => {
+export const stop_timer = async () => {
 	unlisten();
-	const res = await command<StopWatch>('stop_timer', { stop_watch });
+	const res = await command<StopWatch>('stop_timer');
 	const sw = deserialize_sw(res);
 	store.update_stop_watch(sw);
 	store.setClock('00:00:00');
 	return sw;
 };
 
-export const cancel_timer = async (stop_watch: StopWatch) => {
+// Records the running entry and starts the given work content as the next one.
+export const switch_task = async (
+	project_id: DefinedWorkContent['project']['id'],
+	process_id: DefinedWorkContent['process']['id'],
+	sub_process_ids: Array<Process['id']> = [],
+) => {
+	const res = await command<StopWatch>('switch_task', {
+		project_id,
+		process_id,
+		sub_process_ids,
+	});
+	const sw = deserialize_sw(res);
+	await listen_timer_tick();
+	if (is_defined_work_content(sw.work_content)) store.add_to_history(sw.work_content);
+	store.update_stop_watch(sw);
+	return sw;
+};
+
+export const cancel_timer = async () => {
 	unlisten();
-	const res = await command<StopWatch>('cancel_timer', { stop_watch });
+	const res = await command<StopWatch>('cancel_timer');
 	const sw = deserialize_sw(res);
 	store.update_stop_watch(sw);
 	store.setClock('00:00:00');
 	return sw;
 };
 
+export const resolve_idle = async (period: IdlePeriod, choice: IdleChoice) => {
+	const res = await command<StopWatch>('resolve_idle', { period, choice });
+	const sw = deserialize_sw(res);
+	if (sw.status !== TIMER_STATUS.STARTED) {
+		unlisten();
+		store.setClock('00:00:00');
+	}
+	store.update_stop_watch(sw);
+	return sw;
+};
+
+// Pushed whenever the stopwatch held on the Rust side changes, e.g. from the tray.
+listen<StopWatch>(EVENT.STOP_WATCH_CHANGED, async (e) => {
+	if (!store.is_initialized()) return;
+	const sw = deserialize_sw(e.payload);
+	if (is_defined_work_content(sw.work_content)) store.add_to_history(sw.work_content);
+	if (sw.status === TIMER_STATUS.STARTED) {
+		await listen_timer_tick();
+	} else {
+		unlisten();
+		store.setClock('00:00:00');
+	}
+	store.update_stop_watch(sw);
+});
+
+// Actions done while offline are replayed later, the stop watch may have been changed elsewhere meanwhile.
+listen<SyncConflict[]>(EVENT.SYNC_CONFLICTS, (e) => {
+	const lines = e.payload.map((v) => `${v.recorded_at} ${v.action}: ${v.reason}`);
+	alert(['Some actions done while offline could not be replayed as is.', ...lines].join('\n'));
+});
+
+export const apply_pending = async () => {
+	const res = await command<StopWatch>('apply_pending');
+	const sw = deserialize_sw(res);
+	store.update_stop_watch(sw);
+	return sw;
+};
+
+export const discard_pending = async () => {
+	const res = await command<StopWatch>('discard_pending');
+	const sw = deserialize_sw(res);
+	store.update_stop_watch(sw);
+	return sw;
+};
+
+// Dates are `YYYY-MM-DD`, both included, today of the tenant by default.
+export const get_timesheets = (start_date?: string, end_date?: string) => {
+	return command<Timesheet[]>('get_timesheets', { start_date, end_date });
+};
+
+export const create_timesheet = (input: TimesheetInput) => {
+	return command<Timesheet>('create_timesheet', { input });
+};
+
+export const edit_timesheet = (id: Timesheet['id'], input: TimesheetInput) => {
+	return command<Timesheet>('edit_timesheet', { id, input });
+};
+
+export const delete_timesheet = (id: Timesheet['id']) => {
+	return command<void>('delete_timesheet', { id });
+};
+
+// `date` is any day of the period, today by default.
+export const get_summary = (period: SummaryPeriod, date?: string) => {
+	return command<Summary>('get_summary', { period, date });
+};
+
 const deserialize_sw = (sw: StopWatch): StopWatch => {
 	if (!is_valid_project(sw.work_content.project)) sw.work_content.project = undefined;
 	if (!is_valid_project(sw.work_content.process)) sw.work_content.process = undefined;
+	if (!is_valid_project(sw.work_content.process_2)) sw.work_content.process_2 = undefined;
+	if (!is_valid_project(sw.work_content.process_3)) sw.work_content.process_3 = undefined;
 	return sw;
 };
 
-const is_valid_project = (t: WorkContent['project' | 'process']) => t && t.id > 0;
+const is_valid_project = (t: WorkContent['project' | 'process' | 'process_2']) => t && t.id > 0;
+
+// Fired by the global shortcut of the work content switcher.
+export const listen_open_switcher = (handler: () => void) => {
+	return listen(EVENT.OPEN_SWITCHER, handler);
+};
+
+// Fired when the user is back after being idle while the stopwatch was running.
+export const listen_idle_detected = (handler: (period: IdlePeriod) => void) => {
+	return listen<IdlePeriod>(EVENT.IDLE_DETECTED, (e) => handler(e.payload));
+};
+
+// Fired when the stopwatch has been running for long, or late, see the alerts in the preferences.
+export const listen_timer_alert = (handler: (alert: TimerAlert) => void) => {
+	return listen<TimerAlert>(EVENT.TIMER_ALERT, (e) => handler(e.payload));
+};
+
+// Fired when the stopwatch is not running during the working hours.
+export const listen_start_reminder = (handler: (reminder: StartReminder) => void) => {
+	return listen<StartReminder>(EVENT.START_REMINDER, (e) => handler(e.payload));
+};
@@ -53,6 +53,11 @@ export const store = $state({
 		this.add_to_history(wc);
 	},
 
+	update_memo(memo: string) {
+		if (!is_initialized(state)) throw new UninitializedError();
+		state.stop_watch.work_content.memo = memo;
+	},
+
 	add_to_history(work_content: WorkContent) {
 		if (!is_initialized(state)) throw new UninitializedError();
 		if (!is_defined_work_content(work_content)) throw new UndefinedWorkContentError();
@@ -93,6 +98,14 @@ export const is_defined_work_content = (t: WorkContent): t is DefinedWorkContent
 	return t.project !== undefined && t.process !== undefined;
 };
 
+// Ids of the processes of level 2 and 3, down to the deepest set one.
+// Crowdlog sends an id of 0 for unset ones.
+export const sub_process_ids = (t: WorkContent): Array<Process['id']> => {
+	const ids = [t.process_2?.id ?? 0, t.process_3?.id ?? 0];
+	const unset = ids.indexOf(0);
+	return unset === -1 ? ids : ids.slice(0, unset);
+};
+
 class UndefinedWorkContentError extends Error {
 	constructor() {
 		super('Either or both of PROJECT or PROCESS is not defined');
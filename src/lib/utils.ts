@@ -7,3 +7,14 @@ export const KEYBOARD = {
 
 export const is_submittive_key = (key: KeyboardEvent['key']) =>
 	key === KEYBOARD.SPACE || key === KEYBOARD.ENTER;
+
+// Time `minutes` later than a `YYYY-MM-DD HH:MM:SS` one, in the same format.
+export const add_minutes = (time: string, minutes: number) => {
+	const date = new Date(`${time.replace(' ', 'T')}Z`);
+	date.setUTCMinutes(date.getUTCMinutes() + minutes);
+	return date.toISOString().slice(0, 19).replace('T', ' ');
+};
+
+// Minutes as `H:MM`.
+export const to_hours_str = (minutes: number) =>
+	`${Math.floor(minutes / 60)}:${String(minutes % 60).padStart(2, '0')}`;
@@ -1,8 +1,13 @@
-type Mode = 'normal' | 'history' | 'my_patterns';
+import { set_work_content, switch_task } from '$lib/io.svelte';
+import { store, TIMER_STATUS } from '$lib/store.svelte';
+
+type Mode = 'normal' | 'history' | 'my_patterns' | 'search';
 
 const _context = $state({
 	title: '',
 	mode: (localStorage.getItem('mode') ?? 'history') as Mode,
+	// Whether selecting a work content while the stopwatch runs starts a new entry.
+	as_new_entry: localStorage.getItem('as_new_entry') === 'true',
 });
 
 export const context = {
@@ -19,4 +24,25 @@ export const context = {
 		_context.mode = m;
 		localStorage.setItem('mode', m);
 	},
+	get as_new_entry() {
+		return _context.as_new_entry;
+	},
+	set as_new_entry(v: boolean) {
+		_context.as_new_entry = v;
+		localStorage.setItem('as_new_entry', String(v));
+	},
+};
+
+export const is_running = () => store.state.stop_watch.status === TIMER_STATUS.STARTED;
+
+// Switches to the next entry when asked to, otherwise changes the work content of the current one.
+export const select_work_content = (
+	project_id: DefinedWorkContent['project']['id'],
+	process_id: DefinedWorkContent['process']['id'],
+	sub_process_ids: Array<Process['id']> = [],
+) => {
+	if (context.as_new_entry && is_running()) {
+		return switch_task(project_id, process_id, sub_process_ids);
+	}
+	return set_work_content(project_id, process_id, sub_process_ids);
 };
@@ -1,5 +1,4 @@
 import { redirect } from '@sveltejs/kit';
-import { get_processes } from '$lib/io.svelte.js';
 import { store } from '$lib/store.svelte.js';
 import { context } from '../../context.svelte.js';
 
@@ -12,10 +11,7 @@ export async function load({ params }) {
 	const project = store.state.projects.find((p) => p.id === project_id);
 	if (project === undefined) redirect(307, '/set_wc');
 
-	const processes = await get_processes(project_id);
-
 	return {
 		project,
-		processes,
 	};
 }
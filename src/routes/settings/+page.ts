@@ -0,0 +1,8 @@
+import { get_preferences } from '$lib/io.svelte.js';
+
+export async function load() {
+	const preferences = await get_preferences();
+	return {
+		preferences,
+	};
+}
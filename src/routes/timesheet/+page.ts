@@ -0,0 +1,8 @@
+import { get_timesheets } from '$lib/io.svelte.js';
+
+export async function load() {
+	const timesheets = await get_timesheets();
+	return {
+		timesheets,
+	};
+}